@@ -1,9 +1,11 @@
 use std::env;
 use std::fs;
-use std::time::Duration;
+use std::sync::Barrier;
+use std::thread;
+use std::time::{Duration, Instant};
 
 use bytecheck::CheckBytes;
-use criterion::{black_box, criterion_group, criterion_main, Criterion, Throughput};
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
 use pprof::criterion::PProfProfiler;
 use rkyv::{AlignedVec, Archive, Deserialize, Serialize};
 #[cfg(unix)]
@@ -179,9 +181,57 @@ pub fn bench_locked_reads(c: &mut Criterion) {
     });
 }
 
+pub fn bench_concurrent_reads(c: &mut Criterion) {
+    let path = derive_shm_path("mmap_sync_concurrent_reads");
+    let mut synchronizer = Synchronizer::new(path.as_ref());
+    let (data, _) = build_mock_data();
+
+    // Populate data to make it available to read.
+    synchronizer
+        .write(&data, Duration::from_nanos(10))
+        .expect("failed to populate initial data");
+
+    let mut group = c.benchmark_group("synchronizer_concurrent_read");
+    for threads in [1, 4, 16, 64] {
+        group.throughput(Throughput::Elements(threads as u64));
+        for (name, sharded, registered) in [
+            ("unsharded", false, false),
+            ("sharded", true, false),
+            ("registered", false, true),
+        ] {
+            group.bench_with_input(BenchmarkId::new(name, threads), &threads, |b, &threads| {
+                b.iter_custom(|iters| {
+                    // every reader thread reads through its own synchronizer, the same way
+                    // separate reader processes do
+                    let barrier = Barrier::new(threads + 1);
+                    thread::scope(|s| {
+                        for _ in 0..threads {
+                            s.spawn(|| {
+                                let mut synchronizer = Synchronizer::new(path.as_ref())
+                                    .with_sharded_readers(sharded)
+                                    .with_registered_readers(registered);
+                                drop(unsafe { synchronizer.read::<HelloWorld>(false).unwrap() });
+                                barrier.wait();
+                                for _ in 0..iters {
+                                    let archived =
+                                        unsafe { synchronizer.read::<HelloWorld>(false).unwrap() };
+                                    assert_eq!(archived.version, data.version);
+                                }
+                            });
+                        }
+                        barrier.wait();
+                        Instant::now()
+                    })
+                    .elapsed()
+                })
+            });
+        }
+    }
+}
+
 criterion_group! {
     name = benches;
     config = Criterion::default().with_profiler(PProfProfiler::new(100, pprof::criterion::Output::Protobuf));
-    targets = bench_synchronizer, bench_locked_reads, bench_locked_writes
+    targets = bench_synchronizer, bench_locked_reads, bench_locked_writes, bench_concurrent_reads
 }
 criterion_main!(benches);
@@ -15,10 +15,10 @@ fn main() {
     };
 
     // Write data to shared memory
-    let (written, reset) = synchronizer
+    let res = synchronizer
         .write(&data, Duration::from_secs(1))
         .expect("failed to write data");
 
     // Show how many bytes written and whether state was reset
-    println!("written: {} bytes | reset: {}", written, reset);
+    println!("written: {} bytes | reset: {}", res.size(), res.is_reset());
 }
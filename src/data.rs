@@ -1,11 +1,15 @@
 use memmap2::{Mmap, MmapMut};
+use std::cell::RefCell;
+use std::collections::HashMap;
 use std::ffi::{OsStr, OsString};
 use std::fs::{File, OpenOptions};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, PoisonError, RwLock};
 
 #[cfg(unix)]
 use std::os::unix::fs::OpenOptionsExt;
 
-use crate::instance::InstanceVersion;
+use crate::instance::{InstanceVersion, MAX_DATA_INSTANCES};
 use crate::synchronizer::SynchronizerError;
 use crate::synchronizer::SynchronizerError::*;
 use crate::utils;
@@ -15,12 +19,13 @@ use crate::utils;
 pub(crate) struct DataContainer {
     /// Base data path
     path_prefix: OsString,
-    /// Reader's current local instance version
-    version: Option<InstanceVersion>,
-    /// Read-only memory mapped files storing data
-    read_mmaps: [Option<Mmap>; 2],
+    /// Reader's current local instance version along with its publication generation
+    current: Option<(InstanceVersion, u64)>,
+    /// Read-only memory mapped files storing data, reference counted so that owned read results
+    /// keep them alive
+    read_mmaps: [Option<Arc<Mmap>>; MAX_DATA_INSTANCES],
     /// Write-only memory mapped files storing data
-    write_mmaps: [Option<MmapMut>; 2],
+    write_mmaps: [Option<MmapMut>; MAX_DATA_INSTANCES],
 }
 
 impl DataContainer {
@@ -28,9 +33,9 @@ impl DataContainer {
     pub(crate) fn new(path_prefix: &OsStr) -> Self {
         DataContainer {
             path_prefix: path_prefix.into(),
-            version: None,
-            read_mmaps: [None, None],
-            write_mmaps: [None, None],
+            current: None,
+            read_mmaps: Default::default(),
+            write_mmaps: Default::default(),
         }
     }
 
@@ -76,31 +81,133 @@ impl DataContainer {
         Ok(data.len())
     }
 
-    /// Fetch data from mapped data file of given `version`
+    /// Fetch data from mapped data file of given `version` published with `generation`
     #[inline]
     pub(crate) fn data(
         &mut self,
         version: InstanceVersion,
+        generation: u64,
     ) -> Result<(&[u8], bool), SynchronizerError> {
+        let (mmap, switched) = self.mapping(version, generation)?;
+        Ok((&mmap[..version.size()], switched))
+    }
+
+    /// Fetch mapped data file of given `version` published with `generation`, along with whether
+    /// data was switched, keeping the mapping alive for as long as it's used
+    #[inline]
+    pub(crate) fn shared_data(
+        &mut self,
+        version: InstanceVersion,
+        generation: u64,
+    ) -> Result<(Arc<Mmap>, bool), SynchronizerError> {
+        let (mmap, switched) = self.mapping(version, generation)?;
+        Ok((mmap.clone(), switched))
+    }
+
+    /// Fetch mapped data file of given `version` published with `generation`, along with whether
+    /// data was switched since the previous call
+    #[inline]
+    fn mapping(
+        &mut self,
+        version: InstanceVersion,
+        generation: u64,
+    ) -> Result<(&Arc<Mmap>, bool), SynchronizerError> {
         let mmap = &mut self.read_mmaps[version.idx()];
-        let data_size = version.size();
 
         // only open and mmap data file in the following cases:
         // * if it never was opened/mapped before
         // * if current mmap size is smaller than requested data size
-        if mmap.is_none() || mmap.as_ref().unwrap().len() < data_size {
-            let data_file = File::open(version.path(&self.path_prefix)).map_err(FailedDataRead)?;
-            if data_file.metadata().map_err(FailedDataRead)?.len() < data_size as u64 {
-                return Err(FailedEntityRead);
+        if mmap.is_none() || mmap.as_ref().unwrap().len() < version.size() {
+            *mmap = Some(Arc::new(map_data_file(&self.path_prefix, version)?));
+        }
+
+        let new_current = Some((version, generation));
+        let switched = new_current != self.current;
+        self.current = new_current;
+
+        Ok((mmap.as_ref().unwrap(), switched))
+    }
+}
+
+/// Source of unique identifiers of `SharedDataContainer`s
+static NEXT_CONTAINER_ID: AtomicU64 = AtomicU64::new(0);
+
+thread_local! {
+    /// Publication generation of the data read last by the current thread, per identifier of
+    /// `SharedDataContainer`
+    static LAST_GENERATIONS: RefCell<HashMap<u64, u64>> = RefCell::new(HashMap::new());
+}
+
+/// Data container sharing read-only memory mapped data files between threads, so that they can
+/// read data concurrently.
+///
+/// Mappings are reference counted, so that a mapping replaced by a larger one once the data file
+/// grows stays valid for as long as any reader uses it.
+pub(crate) struct SharedDataContainer {
+    /// Base data path
+    path_prefix: OsString,
+    /// Unique identifier of the container, under which threads track data read last
+    id: u64,
+    /// Read-only memory mapped files storing data
+    read_mmaps: [RwLock<Option<Arc<Mmap>>>; MAX_DATA_INSTANCES],
+}
+
+impl SharedDataContainer {
+    /// Create new instance of `SharedDataContainer`
+    pub(crate) fn new(path_prefix: &OsStr) -> Self {
+        SharedDataContainer {
+            path_prefix: path_prefix.into(),
+            id: NEXT_CONTAINER_ID.fetch_add(1, Ordering::Relaxed),
+            read_mmaps: Default::default(),
+        }
+    }
+
+    /// Fetch mapped data file of given `version` published with `generation`, along with whether
+    /// data was switched since the last fetch by the current thread
+    #[inline]
+    pub(crate) fn data(
+        &self,
+        version: InstanceVersion,
+        generation: u64,
+    ) -> Result<(Arc<Mmap>, bool), SynchronizerError> {
+        let data_size = version.size();
+        let switched = LAST_GENERATIONS
+            .with(|last| last.borrow_mut().insert(self.id, generation) != Some(generation));
+
+        let read_mmap = &self.read_mmaps[version.idx()];
+        if let Some(mmap) = read_mmap
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .as_ref()
+        {
+            if mmap.len() >= data_size {
+                return Ok((mmap.clone(), switched));
             }
-            *mmap = Some(unsafe { Mmap::map(&data_file).map_err(FailedDataRead)? });
         }
 
-        let data = &mmap.as_ref().unwrap()[..data_size];
-        let new_version = Some(version);
-        let switched = new_version != self.version;
-        self.version = new_version;
+        // only open and mmap data file if it was never mapped before or current mmap size is
+        // smaller than requested data size, unless another thread did it in the meantime
+        let mut mmap = read_mmap.write().unwrap_or_else(PoisonError::into_inner);
+        if mmap.is_none() || mmap.as_ref().unwrap().len() < data_size {
+            *mmap = Some(Arc::new(map_data_file(&self.path_prefix, version)?));
+        }
+
+        Ok((mmap.as_ref().unwrap().clone(), switched))
+    }
+}
+
+impl Drop for SharedDataContainer {
+    fn drop(&mut self) {
+        // generations tracked by other threads are left behind, as only a few bytes per thread
+        let _ = LAST_GENERATIONS.try_with(|last| last.borrow_mut().remove(&self.id));
+    }
+}
 
-        Ok((data, switched))
+/// Map data file of given `version` for reading, ensuring that it's large enough for the version
+fn map_data_file(path_prefix: &OsStr, version: InstanceVersion) -> Result<Mmap, SynchronizerError> {
+    let data_file = File::open(version.path(path_prefix)).map_err(FailedDataRead)?;
+    if data_file.metadata().map_err(FailedDataRead)?.len() < version.size() as u64 {
+        return Err(FailedEntityRead);
     }
+    unsafe { Mmap::map(&data_file).map_err(FailedDataRead) }
 }
@@ -0,0 +1,100 @@
+use std::sync::atomic::{AtomicU32, AtomicU64};
+use std::time::Duration;
+
+/// Number of busy spins before falling back to sleeping on platforms without `futex`
+#[cfg(not(target_os = "linux"))]
+const SPIN_ATTEMPTS: u32 = 64;
+/// Initial sleep duration on platforms without `futex`, doubled after each unsuccessful attempt
+#[cfg(not(target_os = "linux"))]
+const MIN_BACKOFF: Duration = Duration::from_micros(1);
+/// Maximum sleep duration on platforms without `futex`
+#[cfg(not(target_os = "linux"))]
+const MAX_BACKOFF: Duration = Duration::from_millis(10);
+
+/// Return the half of 64-bit atomic `word` holding its lower 32 bits
+#[inline]
+pub(crate) fn lower_half(word: &AtomicU64) -> &AtomicU32 {
+    let ptr = word as *const AtomicU64 as *const AtomicU32;
+    // lower bits are stored in the second half of the word on big-endian platforms
+    #[cfg(target_endian = "big")]
+    let ptr = unsafe { ptr.add(1) };
+    unsafe { &*ptr }
+}
+
+/// Waits for a change of a 32-bit word shared between processes.
+///
+/// On Linux waiting is done via `futex`, so that waiter gets woken up by `wake_all` right away.
+/// On other platforms waiter spins for a while and then backs off, sleeping for increasing
+/// periods of time.
+pub(crate) struct Waiter {
+    #[cfg(not(target_os = "linux"))]
+    attempt: u32,
+}
+
+impl Waiter {
+    /// Create new `Waiter`
+    pub(crate) fn new() -> Self {
+        Waiter {
+            #[cfg(not(target_os = "linux"))]
+            attempt: 0,
+        }
+    }
+
+    /// Block while `atomic` holds `expected` value until woken up or `timeout` elapses.
+    ///
+    /// Spurious wake ups are possible, so callers must re-check the condition they wait for.
+    #[cfg(target_os = "linux")]
+    pub(crate) fn wait(&mut self, atomic: &AtomicU32, expected: u32, timeout: Duration) {
+        let timeout = libc::timespec {
+            tv_sec: timeout.as_secs() as libc::time_t,
+            tv_nsec: timeout.subsec_nanos() as libc::c_long,
+        };
+        // `FUTEX_PRIVATE_FLAG` is not used, since the word is shared between processes
+        unsafe {
+            libc::syscall(
+                libc::SYS_futex,
+                atomic.as_ptr(),
+                libc::FUTEX_WAIT,
+                expected,
+                &timeout as *const libc::timespec,
+                std::ptr::null::<u32>(),
+                0,
+            );
+        }
+    }
+
+    /// Block while `atomic` holds `expected` value until woken up or `timeout` elapses.
+    ///
+    /// Spurious wake ups are possible, so callers must re-check the condition they wait for.
+    #[cfg(not(target_os = "linux"))]
+    pub(crate) fn wait(&mut self, atomic: &AtomicU32, expected: u32, timeout: Duration) {
+        use std::sync::atomic::Ordering;
+
+        if atomic.load(Ordering::SeqCst) != expected {
+            return;
+        }
+
+        self.attempt = self.attempt.saturating_add(1);
+        if self.attempt <= SPIN_ATTEMPTS {
+            std::hint::spin_loop();
+        } else {
+            let shift = (self.attempt - SPIN_ATTEMPTS).min(31);
+            let backoff = MIN_BACKOFF.saturating_mul(1 << shift).min(MAX_BACKOFF);
+            std::thread::sleep(backoff.min(timeout));
+        }
+    }
+}
+
+/// Wake up all waiters blocked on `atomic`
+#[cfg(target_os = "linux")]
+#[inline]
+pub(crate) fn wake_all(atomic: &AtomicU32) {
+    unsafe {
+        libc::syscall(libc::SYS_futex, atomic.as_ptr(), libc::FUTEX_WAKE, i32::MAX);
+    }
+}
+
+/// Wake up all waiters blocked on `atomic`
+#[cfg(not(target_os = "linux"))]
+#[inline]
+pub(crate) fn wake_all(_atomic: &AtomicU32) {}
@@ -12,47 +12,124 @@
 //!
 //! The `synchronizer` module utilizes this `guard` module to manage memory safety, allowing
 //! users to focus on their application logic.
+use memmap2::Mmap;
 use rkyv::{Archive, Archived};
 use std::ops::Deref;
+use std::ptr::NonNull;
+use std::sync::Arc;
+use std::time::Duration;
 
 use crate::instance::InstanceVersion;
-use crate::state::State;
+use crate::metadata::Metadata;
+use crate::state::{DataHeader, ReadLock, ReadOptions, SharedState, State};
+use crate::synchronizer::SynchronizerError;
 
-/// An RAII implementation of a “scoped read lock” of a `State`
-pub(crate) struct ReadGuard<'a> {
-    state: &'a mut State,
-    version: InstanceVersion,
+/// An RAII implementation of a “scoped read lock” of a `State`, either borrowed or shared
+pub(crate) struct ReadGuard<S: Deref<Target = State>> {
+    state: S,
+    lock: ReadLock,
+    assert_valid: bool,
 }
 
-impl<'a> ReadGuard<'a> {
-    /// Creates new `ReadGuard` with specified parameters
-    pub(crate) fn new(state: &'a mut State, version: InstanceVersion) -> Self {
-        state.rlock(version);
-        ReadGuard { version, state }
+impl<S: Deref<Target = State>> ReadGuard<S> {
+    /// Creates new `ReadGuard` locking current version of the `state` according to `options`,
+    /// asserting that the lock is still valid on drop in debug builds if `assert_valid` is set
+    pub(crate) fn new(
+        state: S,
+        options: &ReadOptions,
+        assert_valid: bool,
+    ) -> Result<Self, SynchronizerError> {
+        let lock = state.rlock_current(options)?;
+        Ok(ReadGuard {
+            state,
+            lock,
+            assert_valid,
+        })
+    }
+
+    /// Indicates whether the lock held by this guard is still valid
+    #[inline]
+    pub(crate) fn is_valid(&self) -> bool {
+        self.state.is_lock_valid(&self.lock)
+    }
+
+    /// Extends lease of the lock held by this guard to expire after `lease` from now
+    #[inline]
+    pub(crate) fn extend_lease(&self, lease: Duration) -> bool {
+        self.state.extend_lease(&self.lock, lease)
+    }
+
+    /// Returns version locked by this guard
+    #[inline]
+    pub(crate) fn version(&self) -> InstanceVersion {
+        self.lock.version()
+    }
+
+    /// Returns publication generation of the version locked by this guard
+    #[inline]
+    pub(crate) fn generation(&self) -> u64 {
+        self.lock.publication()
+    }
+
+    /// Returns header of the data instance locked by this guard
+    #[inline]
+    pub(crate) fn header(&self) -> &DataHeader {
+        self.state.data_header(self.lock.version().idx())
     }
 }
 
-impl Drop for ReadGuard<'_> {
+impl<S: Deref<Target = State>> Drop for ReadGuard<S> {
     /// Unlocks stored `version` when `ReadGuard` goes out of scope
     fn drop(&mut self) {
-        self.state.runlock(self.version);
+        // asserting while already panicking would abort the process instead of unwinding
+        if self.assert_valid && !std::thread::panicking() {
+            debug_assert!(
+                self.is_valid(),
+                "read guard of {:?} outlived grace period and was reset by the writer",
+                self.lock.version()
+            );
+        }
+        self.state.runlock(self.lock);
     }
 }
 
 /// `Synchronizer` result
 pub struct ReadResult<'a, T: Archive> {
-    _guard: ReadGuard<'a>,
+    _guard: ReadGuard<&'a State>,
     entity: &'a Archived<T>,
     switched: bool,
+    /// Shared mapping of the data file, kept alive for as long as `entity` refers to it
+    _mmap: Option<Arc<Mmap>>,
 }
 
 impl<'a, T: Archive> ReadResult<'a, T> {
     /// Creates new `ReadResult` with specified parameters
-    pub(crate) fn new(guard: ReadGuard<'a>, entity: &'a Archived<T>, switched: bool) -> Self {
+    pub(crate) fn new(
+        guard: ReadGuard<&'a State>,
+        entity: &'a Archived<T>,
+        switched: bool,
+    ) -> Self {
         ReadResult {
             _guard: guard,
             entity,
             switched,
+            _mmap: None,
+        }
+    }
+
+    /// Creates new `ReadResult` with `entity` stored in shared mapping `mmap`, which is kept
+    /// alive along with the result
+    pub(crate) fn with_mmap(
+        guard: ReadGuard<&'a State>,
+        entity: &'a Archived<T>,
+        switched: bool,
+        mmap: Arc<Mmap>,
+    ) -> Self {
+        ReadResult {
+            _guard: guard,
+            entity,
+            switched,
+            _mmap: Some(mmap),
         }
     }
 
@@ -60,6 +137,51 @@ impl<'a, T: Archive> ReadResult<'a, T> {
     pub fn is_switched(&self) -> bool {
         self.switched
     }
+
+    /// Returns publication generation of the data, which is incremented by the writer on every
+    /// publication and can be used for ordering and deduplication of data updates
+    pub fn generation(&self) -> u64 {
+        self._guard.generation()
+    }
+
+    /// Indicates whether the data is still guaranteed to be intact.
+    ///
+    /// Returns `false` once the writer has reset the reader counter of the data file after
+    /// `grace_duration` expired, or evicted this reader considering it dead, in which case the
+    /// writer might be overwriting the data while it's being read.
+    pub fn is_still_valid(&self) -> bool {
+        self._guard.is_valid()
+    }
+
+    /// Extends the lease of the data obtained via `Synchronizer::read_with_lease` to expire after
+    /// `lease` from now.
+    ///
+    /// Returns `false` if the result holds no lease, e.g. because the reader could not be
+    /// registered, which is always the case on platforms other than Linux, or if it's no longer
+    /// valid (see `is_still_valid`). Leases should be extended
+    /// before they expire, since the writer may reset readers with expired leases at any time.
+    pub fn extend_lease(&self, lease: Duration) -> bool {
+        self._guard.extend_lease(lease)
+    }
+
+    /// Returns publication `Metadata` of the data, describing when and by whom it was published
+    pub fn metadata(&self) -> Metadata {
+        self._guard.header().metadata()
+    }
+
+    /// Makes `MappedReadResult` for a part of the data, e.g. a single field of the archived
+    /// root, which keeps holding the reader lock until dropped.
+    pub fn map<U: ?Sized, F>(self, f: F) -> MappedReadResult<'a, U>
+    where
+        F: FnOnce(&Archived<T>) -> &U,
+    {
+        MappedReadResult {
+            value: f(self.entity),
+            _guard: self._guard,
+            switched: self.switched,
+            _mmap: self._mmap,
+        }
+    }
 }
 
 impl<T: Archive> Deref for ReadResult<'_, T> {
@@ -70,3 +192,124 @@ impl<T: Archive> Deref for ReadResult<'_, T> {
         self.entity
     }
 }
+
+/// `ReadResult` projected onto a part of the data via `ReadResult::map`
+pub struct MappedReadResult<'a, U: ?Sized> {
+    _guard: ReadGuard<&'a State>,
+    value: &'a U,
+    switched: bool,
+    /// Shared mapping of the data file, kept alive for as long as `value` refers to it
+    _mmap: Option<Arc<Mmap>>,
+}
+
+impl<'a, U: ?Sized> MappedReadResult<'a, U> {
+    /// Indicates whether data was switched during last read
+    pub fn is_switched(&self) -> bool {
+        self.switched
+    }
+
+    /// Returns publication generation of the data, see `ReadResult::generation`
+    pub fn generation(&self) -> u64 {
+        self._guard.generation()
+    }
+
+    /// Indicates whether the data is still guaranteed to be intact, see
+    /// `ReadResult::is_still_valid`
+    pub fn is_still_valid(&self) -> bool {
+        self._guard.is_valid()
+    }
+
+    /// Extends the lease of the data, see `ReadResult::extend_lease`
+    pub fn extend_lease(&self, lease: Duration) -> bool {
+        self._guard.extend_lease(lease)
+    }
+
+    /// Returns publication `Metadata` of the data, describing when and by whom it was published
+    pub fn metadata(&self) -> Metadata {
+        self._guard.header().metadata()
+    }
+
+    /// Makes `MappedReadResult` for a narrower part of the data, see `ReadResult::map`
+    pub fn map<V: ?Sized, F>(self, f: F) -> MappedReadResult<'a, V>
+    where
+        F: FnOnce(&U) -> &V,
+    {
+        MappedReadResult {
+            value: f(self.value),
+            _guard: self._guard,
+            switched: self.switched,
+            _mmap: self._mmap,
+        }
+    }
+}
+
+impl<U: ?Sized> Deref for MappedReadResult<'_, U> {
+    type Target = U;
+
+    /// Dereferences stored `value` for easier access
+    fn deref(&self) -> &U {
+        self.value
+    }
+}
+
+/// `SharedReader` result owning the reader lock along with the mappings of the state and data
+pub struct OwnedReadResult<T: Archive> {
+    _guard: ReadGuard<SharedState>,
+    entity: NonNull<Archived<T>>,
+    switched: bool,
+    /// Shared mapping of the data file, kept alive for as long as `entity` refers to it
+    _mmap: Arc<Mmap>,
+}
+
+// SAFETY: the entity is only ever accessed through shared references, while the mappings
+// it's stored in are kept alive by the result itself
+unsafe impl<T: Archive> Send for OwnedReadResult<T> where Archived<T>: Sync {}
+unsafe impl<T: Archive> Sync for OwnedReadResult<T> where Archived<T>: Sync {}
+
+impl<T: Archive> OwnedReadResult<T> {
+    /// Creates new `OwnedReadResult` with `entity` stored in shared mapping `mmap`
+    pub(crate) fn new(
+        guard: ReadGuard<SharedState>,
+        entity: &Archived<T>,
+        switched: bool,
+        mmap: Arc<Mmap>,
+    ) -> Self {
+        OwnedReadResult {
+            _guard: guard,
+            entity: NonNull::from(entity),
+            switched,
+            _mmap: mmap,
+        }
+    }
+
+    /// Indicates whether data was switched during last read
+    pub fn is_switched(&self) -> bool {
+        self.switched
+    }
+
+    /// Returns publication generation of the data, see `ReadResult::generation`
+    pub fn generation(&self) -> u64 {
+        self._guard.generation()
+    }
+
+    /// Indicates whether the data is still guaranteed to be intact, see
+    /// `ReadResult::is_still_valid`
+    pub fn is_still_valid(&self) -> bool {
+        self._guard.is_valid()
+    }
+
+    /// Returns publication `Metadata` of the data, describing when and by whom it was published
+    pub fn metadata(&self) -> Metadata {
+        self._guard.header().metadata()
+    }
+}
+
+impl<T: Archive> Deref for OwnedReadResult<T> {
+    type Target = Archived<T>;
+
+    /// Dereferences stored `entity` for easier access
+    fn deref(&self) -> &Archived<T> {
+        // SAFETY: the entity is stored in the mapping kept alive by the result
+        unsafe { self.entity.as_ref() }
+    }
+}
@@ -0,0 +1,109 @@
+//! The `heartbeat` module allows readers to tell apart a writer which has nothing new to publish
+//! from a writer which is gone.
+//!
+//! Writer periodically records the current wall-clock time within the memory mapped state, either
+//! by calling `Synchronizer::heartbeat` manually or by running a background `Heartbeat` thread
+//! started with `Synchronizer::spawn_heartbeat`. Every write counts as a heartbeat as well.
+//! Readers check the time of the last heartbeat with `Synchronizer::is_writer_alive`.
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, RecvTimeoutError, Sender};
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+use crate::locks::LockDisabled;
+use crate::state::StateContainer;
+
+/// Handle of the background thread recording writer heartbeats, created by
+/// `Synchronizer::spawn_heartbeat`.
+///
+/// The thread is stopped once the handle is dropped. Since heartbeats are only meaningful while
+/// the writer owns the state, the thread also stops on its own once the writer releases write lock
+/// via `Synchronizer::release_write_lock` or gets dropped, or once it's fenced out by another
+/// writer.
+pub struct Heartbeat {
+    /// Channel used for stopping the thread, disconnected on drop
+    stop: Option<Sender<()>>,
+    /// Background thread recording heartbeats
+    thread: Option<JoinHandle<()>>,
+}
+
+impl Heartbeat {
+    /// Spawns thread recording heartbeats into `state_container` every `interval` on behalf of
+    /// the writer holding write lock with given `fencing_epoch`, until `released` is raised
+    pub(crate) fn spawn(
+        mut state_container: StateContainer<LockDisabled>,
+        interval: Duration,
+        fencing_epoch: Option<u64>,
+        released: Arc<AtomicBool>,
+    ) -> Self {
+        let (stop, stopped) = mpsc::channel::<()>();
+        let thread = thread::spawn(move || {
+            let Ok(state) = state_container.state::<false>(false) else {
+                return;
+            };
+            while let Err(RecvTimeoutError::Timeout) = stopped.recv_timeout(interval) {
+                let fenced_out = fencing_epoch.is_some_and(|epoch| state.fencing_epoch() != epoch);
+                if fenced_out || released.load(Ordering::SeqCst) {
+                    break;
+                }
+                state.heartbeat();
+            }
+        });
+
+        Heartbeat {
+            stop: Some(stop),
+            thread: Some(thread),
+        }
+    }
+}
+
+impl Drop for Heartbeat {
+    /// Stops the background thread and waits for it to finish
+    fn drop(&mut self) {
+        drop(self.stop.take());
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::locks::Failover;
+    use std::fs;
+    use std::time::{Instant, SystemTime};
+
+    #[test]
+    fn heartbeat_stops_once_writer_is_fenced_out() {
+        static PATH: &str = "/tmp/heartbeat_fencing_test";
+        fs::remove_file(PATH.to_owned() + "_state").unwrap_or_default();
+        let mut primary = StateContainer::<Failover>::new(PATH.as_ref());
+        let mut standby = StateContainer::<Failover>::new(PATH.as_ref());
+        let mut reader = StateContainer::<LockDisabled>::new(PATH.as_ref());
+
+        let (_, epoch) = primary.write_state(true).unwrap();
+        let _heartbeat = Heartbeat::spawn(
+            primary.detached(),
+            Duration::from_millis(1),
+            epoch,
+            Arc::default(),
+        );
+        let state = reader.state::<false>(false).unwrap();
+        let since = SystemTime::now();
+        let deadline = Instant::now() + Duration::from_secs(10);
+        while state.last_heartbeat() <= Some(since) {
+            assert!(Instant::now() < deadline, "no heartbeat recorded");
+            thread::sleep(Duration::from_millis(1));
+        }
+
+        // primary loses its lock without noticing, so that standby takes over
+        primary.release_write_lock().unwrap();
+        standby.write_state(true).unwrap();
+        thread::sleep(Duration::from_millis(20));
+        let last_heartbeat = state.last_heartbeat();
+        thread::sleep(Duration::from_millis(20));
+        assert_eq!(state.last_heartbeat(), last_heartbeat);
+    }
+}
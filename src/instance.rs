@@ -4,14 +4,18 @@ use crate::synchronizer::SynchronizerError;
 use crate::synchronizer::SynchronizerError::*;
 
 /// `InstanceVersion` represents data instance and consists of the following components:
-/// - data idx (0 or 1)   - 1 bit
+/// - data idx (0 to 7)   - 3 bits
 /// - data size (<549 GB) - 39 bits
-/// - data checksum       - 24 bits
+/// - data checksum       - 22 bits
 #[derive(Clone, Copy, Debug, PartialEq)]
 pub struct InstanceVersion(pub(crate) u64);
 
+const DATA_IDX_BITS: usize = 3;
 const DATA_SIZE_BITS: usize = 39;
-const DATA_CHECKSUM_BITS: usize = 24;
+const DATA_CHECKSUM_BITS: usize = 22;
+
+/// Maximum number of data instances, each stored in a separate data file
+pub(crate) const MAX_DATA_INSTANCES: usize = 1 << DATA_IDX_BITS;
 
 impl InstanceVersion {
     /// Create new `InstanceVersion` from data instance `idx`, `size` and `checksum`
@@ -23,33 +27,33 @@ impl InstanceVersion {
     ) -> Result<InstanceVersion, SynchronizerError> {
         let mut res: u64 = 0;
 
-        if idx > 1 || size >= 1 << DATA_SIZE_BITS {
+        if idx >= MAX_DATA_INSTANCES || size >= 1 << DATA_SIZE_BITS {
             return Err(InvalidInstanceVersionParams);
         }
 
-        res |= (idx as u64) & 1;
-        res |= ((size as u64) & ((1 << DATA_SIZE_BITS) - 1)) << 1;
-        res |= (checksum & ((1 << DATA_CHECKSUM_BITS) - 1)) << (DATA_SIZE_BITS + 1);
+        res |= (idx as u64) & ((1 << DATA_IDX_BITS) - 1);
+        res |= ((size as u64) & ((1 << DATA_SIZE_BITS) - 1)) << DATA_IDX_BITS;
+        res |= (checksum & ((1 << DATA_CHECKSUM_BITS) - 1)) << (DATA_IDX_BITS + DATA_SIZE_BITS);
 
         Ok(InstanceVersion(res))
     }
 
-    /// Get data instance `idx` (0 or 1)
+    /// Get data instance `idx` (0 to 7)
     #[inline]
     pub(crate) fn idx(&self) -> usize {
-        self.0 as usize & 1
+        self.0 as usize & ((1 << DATA_IDX_BITS) - 1)
     }
 
     /// Get data instance `size`
     #[inline]
     pub(crate) fn size(&self) -> usize {
-        (self.0 as usize >> 1) & ((1 << DATA_SIZE_BITS) - 1)
+        (self.0 as usize >> DATA_IDX_BITS) & ((1 << DATA_SIZE_BITS) - 1)
     }
 
     /// Get data instance `checksum`
     #[cfg(test)]
     pub(crate) fn checksum(&self) -> u64 {
-        self.0 >> (DATA_SIZE_BITS + 1)
+        self.0 >> (DATA_IDX_BITS + DATA_SIZE_BITS)
     }
 
     /// Get data instance `path`
@@ -90,7 +94,7 @@ mod tests {
 
     proptest! {
         #[test]
-        fn test_new(idx in 0..2usize, size in 0..1usize << DATA_SIZE_BITS, checksum in 0..u64::MAX) {
+        fn test_new(idx in 0..MAX_DATA_INSTANCES, size in 0..1usize << DATA_SIZE_BITS, checksum in 0..u64::MAX) {
             let v = InstanceVersion::new(idx, size, checksum).unwrap();
 
             assert_eq!(idx, v.idx());
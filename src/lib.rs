@@ -11,9 +11,18 @@
 //!
 //! To get started with `mmap-sync`, please see the [examples](https://github.com/cloudflare/mmap-sync/tree/main/examples) provided.
 mod data;
+mod futex;
 pub mod guard;
+pub mod heartbeat;
 pub mod instance;
 pub mod locks;
+pub mod metadata;
+mod registry;
+pub mod seqlock;
+pub mod shared;
 mod state;
+#[cfg(feature = "tokio")]
+#[cfg_attr(docsrs, doc(cfg(feature = "tokio")))]
+pub mod stream;
 pub mod synchronizer;
 mod utils;
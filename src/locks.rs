@@ -11,25 +11,94 @@
 
 #[cfg(unix)]
 use std::os::fd::AsRawFd;
+#[cfg(unix)]
+use std::time::Instant;
 use std::{
     fs::File,
     ops::{Deref, DerefMut},
+    time::Duration,
 };
 
 use memmap2::MmapMut;
 
 use crate::synchronizer::SynchronizerError;
 
+/// Memory mapped state file, handed over to a `WriteLockStrategy` on creation.
+///
+/// Strategies own the mapping and provide write access to it through their lock guards, while
+/// the state stored within the mapping is only ever modified by this crate.
+pub struct StateMmap(MmapMut);
+
+impl StateMmap {
+    /// Wrap memory mapped state file
+    pub(crate) fn new(mmap: MmapMut) -> Self {
+        StateMmap(mmap)
+    }
+
+    /// Return raw pointer to the mapped state for modification
+    #[inline]
+    pub(crate) fn as_mut_ptr(&mut self) -> *mut u8 {
+        self.0.as_mut_ptr()
+    }
+}
+
+impl Deref for StateMmap {
+    type Target = [u8];
+
+    #[inline]
+    fn deref(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+/// Mode of write lock acquisition, set via `Synchronizer::with_lock_mode`
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum LockMode {
+    /// Fail with `WriteLockConflict` right away if the lock is held by another writer
+    #[default]
+    NonBlocking,
+    /// Wait until the lock is released by another writer
+    Blocking,
+    /// Wait up to given duration for the lock to be released by another writer, failing with
+    /// `WriteLockConflict` afterwards
+    Timeout(Duration),
+}
+
 /// The write lock strategy supports different lock implementations which can be chosen based on
 /// the guarantees required, platform support, and performance constraints.
 ///
-/// Note: the lock implementations are sealed to avoid committing to a specific lock interface.
-#[allow(private_bounds)]
-pub trait WriteLockStrategy<'a>: WriteLockStrategySealed<'a> {}
+/// Strategies shipped within this module cover common cases, while custom strategies, e.g.
+/// coordinating writers via an external orchestration layer, can be plugged in by implementing
+/// this trait.
+///
+/// # Guard semantics
+/// The state file is mapped once per `Synchronizer` and handed over to the strategy via `new`.
+/// Every write calls `lock` first and accesses the state only through the returned guard, which
+/// is dropped at the end of the write. Dropping the guard must not release the lock: once
+/// acquired, the lock is expected to be held across writes until `release` is called or the
+/// strategy is dropped, so that `lock` returns right away on subsequent writes and reports
+/// whether the lock is held via `is_locked`. Readers never call `lock` and access the state
+/// via `read` instead.
+///
+/// # Safety
+/// The crate relies on the lock for memory safety of readers, hence implementations must uphold
+/// the following contract:
+/// - at most one writer across all processes holds the lock for a given state file at any time,
+///   unless the strategy documents that this is left to the caller, as [`LockDisabled`] does;
+/// - `lock` only returns a guard while the lock is held, and the guard derefs to the `StateMmap`
+///   handed over via `new`;
+/// - `is_locked` returns `true` only while the lock is held;
+/// - `read` returns the whole mapping handed over via `new`.
+pub unsafe trait WriteLockStrategy<'a> {
+    /// Guard providing write access to the mapped state while the lock is held
+    type Guard: DerefMut<Target = StateMmap> + 'a;
+
+    /// Whether acquisitions of the lock are fenced, i.e. each acquisition advances fencing epoch
+    /// stored within the state, and writers holding stale epoch are refused to publish.
+    const FENCING: bool = false;
 
-/// Sealed trait i
-pub(crate) trait WriteLockStrategySealed<'a> {
-    type Guard: DerefMut<Target = MmapMut> + 'a;
+    /// Mode of lock acquisition used unless set via `Synchronizer::with_lock_mode`.
+    const DEFAULT_LOCK_MODE: LockMode = LockMode::NonBlocking;
 
     /// Create a new instance of this lock strategy.
     ///
@@ -37,31 +106,52 @@ pub(crate) trait WriteLockStrategySealed<'a> {
     ///
     /// The `file` parameter is required because lock strategies depending on `flock` must hold
     /// the file descriptor open so the kernel does not release the lock.
-    fn new(mmap: MmapMut, file: File) -> Self;
+    fn new(mmap: StateMmap, file: File) -> Self;
 
     /// Provide read access to mmaped memory.
     fn read(&'a self) -> &'a [u8];
 
-    /// Acquire the lock as specified by the lock strategy.
+    /// Indicate whether the lock is currently held, so that `lock` returns without acquiring it.
+    ///
+    /// Strategies which don't actually lock always return `true`.
+    fn is_locked(&self) -> bool;
+
+    /// Acquire the lock as specified by the lock strategy, waiting for it according to `mode`.
     ///
     /// On success, return a lock guard which can be used to access the underlying mmaped memory
     /// via [`Deref`]/[`DerefMut`].
-    fn lock(&'a mut self) -> Result<Self::Guard, SynchronizerError>;
+    fn lock(&'a mut self, mode: LockMode) -> Result<Self::Guard, SynchronizerError>;
+
+    /// Release the lock if it is held, so that another writer can acquire it.
+    ///
+    /// The lock is acquired again on the next call to `lock`.
+    fn release(&mut self) -> Result<(), SynchronizerError>;
 }
 
+/// Marker of write lock strategies which actually exclude concurrent writers, unlike
+/// [`LockDisabled`], required for enabling strict mode via `Synchronizer::with_strict_mode`.
+///
+/// # Safety
+/// Implementations must guarantee that at most one writer across all processes holds the lock
+/// for a given state file at any time, as `Synchronizer::safe_read` relies on it for memory
+/// safety.
+pub unsafe trait ExclusiveLockStrategy<'a>: WriteLockStrategy<'a> {}
+
 /// Lock protection is disabled.
 ///
 /// # Safety
 /// Callers must ensure that there is only a single active writer. For example, the caller might
 /// ensure that only one process attempts to write to the synchronizer, and ensure that multiple
 /// instances of the process are not spawned.
-pub struct LockDisabled(MmapMut);
+pub struct LockDisabled(StateMmap);
 
-impl<'a> WriteLockStrategySealed<'a> for LockDisabled {
+// SAFETY: single writer is guaranteed by the caller, the rest of the contract is upheld by
+// handing out the mapping as is
+unsafe impl<'a> WriteLockStrategy<'a> for LockDisabled {
     type Guard = DisabledGuard<'a>;
 
     #[inline]
-    fn new(mmap: MmapMut, _file: File) -> Self {
+    fn new(mmap: StateMmap, _file: File) -> Self {
         // No need to hold the file descriptor because lock functionality is disabled.
         Self(mmap)
     }
@@ -72,17 +162,26 @@ impl<'a> WriteLockStrategySealed<'a> for LockDisabled {
     }
 
     #[inline]
-    fn lock(&'a mut self) -> Result<Self::Guard, SynchronizerError> {
+    fn is_locked(&self) -> bool {
+        true
+    }
+
+    #[inline]
+    fn lock(&'a mut self, _mode: LockMode) -> Result<Self::Guard, SynchronizerError> {
         Ok(DisabledGuard(&mut self.0))
     }
-}
 
-impl WriteLockStrategy<'_> for LockDisabled {}
+    #[inline]
+    fn release(&mut self) -> Result<(), SynchronizerError> {
+        Ok(())
+    }
+}
 
-pub struct DisabledGuard<'a>(&'a mut MmapMut);
+/// Guard of [`LockDisabled`] providing write access to the mapped state without any locking.
+pub struct DisabledGuard<'a>(&'a mut StateMmap);
 
 impl Deref for DisabledGuard<'_> {
-    type Target = MmapMut;
+    type Target = StateMmap;
 
     fn deref(&self) -> &Self::Target {
         &*self.0
@@ -95,23 +194,25 @@ impl DerefMut for DisabledGuard<'_> {
     }
 }
 
-/// Acquire the lock. Once acquired, hold the lock until dropped.
+/// Acquire the lock. Once acquired, hold the lock until dropped or explicitly released via
+/// `Synchronizer::release_write_lock`.
 ///
 /// The `flock` API holds the lock as long as the file descriptor is open, and closes the lock
 /// when the descriptor is closed. The descriptor is automatically closed when `File` is dropped.
 #[cfg(unix)]
 pub struct SingleWriter {
-    mmap: MmapMut,
+    mmap: StateMmap,
     file: File,
     locked: bool,
 }
 
 #[cfg(unix)]
-impl<'a> WriteLockStrategySealed<'a> for SingleWriter {
+// SAFETY: exclusive `flock` is held from successful `lock` until `release` or drop
+unsafe impl<'a> WriteLockStrategy<'a> for SingleWriter {
     type Guard = SingleWriterGuard<'a>;
 
     #[inline]
-    fn new(mmap: MmapMut, file: File) -> Self {
+    fn new(mmap: StateMmap, file: File) -> Self {
         Self {
             mmap,
             file,
@@ -125,7 +226,12 @@ impl<'a> WriteLockStrategySealed<'a> for SingleWriter {
     }
 
     #[inline]
-    fn lock(&'a mut self) -> Result<Self::Guard, SynchronizerError> {
+    fn is_locked(&self) -> bool {
+        self.locked
+    }
+
+    #[inline]
+    fn lock(&'a mut self, mode: LockMode) -> Result<Self::Guard, SynchronizerError> {
         // We already hold the lock, so return success.
         if self.locked {
             return Ok(SingleWriterGuard(&mut self.mmap));
@@ -133,27 +239,238 @@ impl<'a> WriteLockStrategySealed<'a> for SingleWriter {
 
         // Acquire the lock for the first time.
         // Note: the file descriptor must remain open to hold the lock.
-        match unsafe { libc::flock(self.file.as_raw_fd(), libc::LOCK_EX | libc::LOCK_NB) } {
-            0 => {
-                // Hold the lock until this structure is dropped.
-                self.locked = true;
-                Ok(SingleWriterGuard(&mut self.mmap))
+        flock_exclusive(&self.file, mode)?;
+
+        // Hold the lock until this structure is dropped or the lock is released.
+        self.locked = true;
+        Ok(SingleWriterGuard(&mut self.mmap))
+    }
+
+    #[inline]
+    fn release(&mut self) -> Result<(), SynchronizerError> {
+        if self.locked {
+            match unsafe { libc::flock(self.file.as_raw_fd(), libc::LOCK_UN) } {
+                0 => self.locked = false,
+                _ => {
+                    return Err(SynchronizerError::FailedStateRead(
+                        std::io::Error::last_os_error(),
+                    ))
+                }
             }
-            _ => Err(SynchronizerError::WriteLockConflict),
         }
+        Ok(())
+    }
+}
+
+// SAFETY: the lock is held exclusively, see `WriteLockStrategy` implementation
+#[cfg(unix)]
+unsafe impl<'a> ExclusiveLockStrategy<'a> for SingleWriter {}
+
+/// Acquire the lock with fencing, allowing a hot-standby writer to take over when the primary
+/// writer dies.
+///
+/// The lock is held the same way as [`SingleWriter`], but writers wait for the lock to be
+/// released by default, so that standby blocks on its first write until the primary is gone.
+/// The first acquisition of the lock by each writer advances fencing epoch stored within the
+/// state, and writes of a writer whose epoch has been superseded by a newer acquisition fail with
+/// `FencedOut`, even after it released the lock, so that a former primary, e.g. paused while its
+/// lock was taken over, never replaces a version published by its successor.
+///
+/// Fencing is best effort beyond that: a former primary resuming in the middle of a write may
+/// still overwrite the data file acquired by its successor, so exclusive access to data files
+/// relies on the lock itself.
+#[cfg(unix)]
+pub struct Failover(SingleWriter);
+
+#[cfg(unix)]
+// SAFETY: locking is delegated to `SingleWriter`
+unsafe impl<'a> WriteLockStrategy<'a> for Failover {
+    type Guard = SingleWriterGuard<'a>;
+
+    const FENCING: bool = true;
+
+    const DEFAULT_LOCK_MODE: LockMode = LockMode::Blocking;
+
+    #[inline]
+    fn new(mmap: StateMmap, file: File) -> Self {
+        Self(SingleWriter::new(mmap, file))
+    }
+
+    #[inline]
+    fn read(&'a self) -> &'a [u8] {
+        self.0.read()
+    }
+
+    #[inline]
+    fn is_locked(&self) -> bool {
+        self.0.is_locked()
+    }
+
+    #[inline]
+    fn lock(&'a mut self, mode: LockMode) -> Result<Self::Guard, SynchronizerError> {
+        self.0.lock(mode)
+    }
+
+    #[inline]
+    fn release(&mut self) -> Result<(), SynchronizerError> {
+        self.0.release()
+    }
+}
+
+// SAFETY: the lock is held exclusively, see `WriteLockStrategy` implementation
+#[cfg(unix)]
+unsafe impl<'a> ExclusiveLockStrategy<'a> for Failover {}
+
+/// Acquire the lock using open file description (OFD) record locks. Once acquired, hold the lock
+/// until dropped or explicitly released via `Synchronizer::release_write_lock`.
+///
+/// Unlike classic POSIX record locks, which are owned by the process and thus never conflict
+/// between threads of the same process, OFD locks are owned by the open file description. Hence
+/// several threads of one process owning separate `Synchronizer`s exclude each other the same
+/// way separate processes do. OFD locks are independent of `flock`, so this strategy must not be
+/// mixed with [`SingleWriter`] for the same state file.
+#[cfg(target_os = "linux")]
+pub struct OfdWriter {
+    mmap: StateMmap,
+    file: File,
+    locked: bool,
+}
+
+#[cfg(target_os = "linux")]
+// SAFETY: exclusive OFD lock is held from successful `lock` until `release` or drop
+unsafe impl<'a> WriteLockStrategy<'a> for OfdWriter {
+    type Guard = SingleWriterGuard<'a>;
+
+    #[inline]
+    fn new(mmap: StateMmap, file: File) -> Self {
+        Self {
+            mmap,
+            file,
+            locked: false,
+        }
+    }
+
+    #[inline]
+    fn read(&'a self) -> &'a [u8] {
+        &self.mmap
     }
+
+    #[inline]
+    fn is_locked(&self) -> bool {
+        self.locked
+    }
+
+    #[inline]
+    fn lock(&'a mut self, mode: LockMode) -> Result<Self::Guard, SynchronizerError> {
+        // We already hold the lock, so return success.
+        if self.locked {
+            return Ok(SingleWriterGuard(&mut self.mmap));
+        }
+
+        // Acquire the lock for the first time.
+        // Note: the file descriptor must remain open to hold the lock.
+        let file = &self.file;
+        lock_exclusive(mode, |wait| {
+            let cmd = if wait {
+                libc::F_OFD_SETLKW
+            } else {
+                libc::F_OFD_SETLK
+            };
+            ofd_lock(file, cmd, libc::F_WRLCK)
+        })?;
+
+        // Hold the lock until this structure is dropped or the lock is released.
+        self.locked = true;
+        Ok(SingleWriterGuard(&mut self.mmap))
+    }
+
+    #[inline]
+    fn release(&mut self) -> Result<(), SynchronizerError> {
+        if self.locked {
+            ofd_lock(&self.file, libc::F_OFD_SETLK, libc::F_UNLCK)
+                .map_err(SynchronizerError::FailedStateRead)?;
+            self.locked = false;
+        }
+        Ok(())
+    }
+}
+
+// SAFETY: the lock is held exclusively, see `WriteLockStrategy` implementation
+#[cfg(target_os = "linux")]
+unsafe impl<'a> ExclusiveLockStrategy<'a> for OfdWriter {}
+
+/// Apply OFD lock operation `cmd` of `lock_type` to the whole `file`
+#[cfg(target_os = "linux")]
+fn ofd_lock(file: &File, cmd: libc::c_int, lock_type: libc::c_int) -> std::io::Result<()> {
+    // OFD locks require `l_pid` to be zero
+    let mut lock: libc::flock = unsafe { std::mem::zeroed() };
+    lock.l_type = lock_type as libc::c_short;
+    lock.l_whence = libc::SEEK_SET as libc::c_short;
+    match unsafe { libc::fcntl(file.as_raw_fd(), cmd, &lock as *const libc::flock) } {
+        0 => Ok(()),
+        _ => Err(std::io::Error::last_os_error()),
+    }
+}
+
+/// Interval between attempts to acquire the lock with a timeout, as neither `flock` nor `fcntl`
+/// can wait with one
+#[cfg(unix)]
+const LOCK_RETRY_INTERVAL: Duration = Duration::from_millis(1);
+
+/// Acquire exclusive `flock` of `file`, waiting for it according to `mode`
+#[cfg(unix)]
+fn flock_exclusive(file: &File, mode: LockMode) -> Result<(), SynchronizerError> {
+    lock_exclusive(mode, |wait| {
+        let operation = match wait {
+            true => libc::LOCK_EX,
+            false => libc::LOCK_EX | libc::LOCK_NB,
+        };
+        match unsafe { libc::flock(file.as_raw_fd(), operation) } {
+            0 => Ok(()),
+            _ => Err(std::io::Error::last_os_error()),
+        }
+    })
 }
 
+/// Acquire exclusive lock using `try_lock`, waiting for it according to `mode`.
+///
+/// `try_lock` is called with `true` if it should block until the lock is acquired.
 #[cfg(unix)]
-impl WriteLockStrategy<'_> for SingleWriter {}
+fn lock_exclusive(
+    mode: LockMode,
+    mut try_lock: impl FnMut(bool) -> std::io::Result<()>,
+) -> Result<(), SynchronizerError> {
+    let deadline = match mode {
+        LockMode::NonBlocking => Some(Instant::now()),
+        LockMode::Blocking => None,
+        LockMode::Timeout(timeout) => Some(Instant::now() + timeout),
+    };
+
+    loop {
+        let err = match try_lock(deadline.is_none()) {
+            Ok(()) => return Ok(()),
+            Err(err) => err,
+        };
+
+        match deadline {
+            // blocking lock only fails when interrupted by a signal, in which case it's retried
+            None if err.kind() == std::io::ErrorKind::Interrupted => {}
+            Some(deadline) if Instant::now() < deadline => {
+                let remaining = deadline.saturating_duration_since(Instant::now());
+                std::thread::sleep(remaining.min(LOCK_RETRY_INTERVAL));
+            }
+            _ => return Err(SynchronizerError::WriteLockConflict),
+        }
+    }
+}
 
 /// A simple guard which does not release the lock upon being dropped.
 #[cfg(unix)]
-pub struct SingleWriterGuard<'a>(&'a mut MmapMut);
+pub struct SingleWriterGuard<'a>(&'a mut StateMmap);
 
 #[cfg(unix)]
 impl Deref for SingleWriterGuard<'_> {
-    type Target = MmapMut;
+    type Target = StateMmap;
 
     fn deref(&self) -> &Self::Target {
         &*self.0
@@ -0,0 +1,67 @@
+//! The `metadata` module describes publications of data made by the writer.
+//!
+//! Along with each `InstanceVersion`, the writer records when and by whom the data was published.
+//! Readers can use this information to detect data feeds which went stale, e.g. because the
+//! writer stopped publishing, and to tell apart writers running on different hosts.
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::utils;
+
+/// `Metadata` of data publication recorded by the writer on every write
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Metadata {
+    published_at: SystemTime,
+    writer_pid: u32,
+    hostname_hash: u64,
+}
+
+impl Metadata {
+    /// Creates `Metadata` describing publication made by the current process right now
+    pub(crate) fn current() -> Self {
+        Metadata {
+            published_at: SystemTime::now(),
+            writer_pid: std::process::id(),
+            hostname_hash: utils::hostname_hash(),
+        }
+    }
+
+    /// Creates `Metadata` from its raw representation stored within the state
+    pub(crate) fn from_raw(published_at_nanos: u64, writer_pid: u32, hostname_hash: u64) -> Self {
+        Metadata {
+            published_at: UNIX_EPOCH + Duration::from_nanos(published_at_nanos),
+            writer_pid,
+            hostname_hash,
+        }
+    }
+
+    /// Returns publication time in nanoseconds since Unix epoch, as stored within the state
+    pub(crate) fn published_at_nanos(&self) -> u64 {
+        utils::unix_nanos(self.published_at)
+    }
+
+    /// Returns wall-clock time at which the data was published
+    pub fn published_at(&self) -> SystemTime {
+        self.published_at
+    }
+
+    /// Returns time elapsed since the data was published according to the local wall clock.
+    ///
+    /// Zero is returned if the publication time is in the future, e.g. due to clock skew
+    /// between hosts sharing the files.
+    pub fn age(&self) -> Duration {
+        SystemTime::now()
+            .duration_since(self.published_at)
+            .unwrap_or_default()
+    }
+
+    /// Returns process id of the writer which published the data
+    pub fn writer_pid(&self) -> u32 {
+        self.writer_pid
+    }
+
+    /// Returns hash of the hostname of the writer which published the data, or zero if the
+    /// hostname is not available
+    pub fn hostname_hash(&self) -> u64 {
+        self.hostname_hash
+    }
+}
@@ -0,0 +1,387 @@
+use std::cell::Cell;
+use std::sync::atomic::{AtomicU32, AtomicU64, Ordering};
+
+use crate::utils;
+
+/// Number of entries in the registry of readers
+pub(crate) const MAX_REGISTERED_READERS: usize = 256;
+
+/// Entry `tag` of a free entry
+const ENTRY_FREE: u64 = 0;
+/// Bit of entry `tag` marking an entry being claimed, whose remaining fields are not filled in
+/// yet. Lower bits of such a tag store lower bits of the claiming process pid namespace.
+const ENTRY_BUSY: u64 = 1 << 31;
+/// Bits of entry `tag` storing either registration sequence or pid namespace of a busy entry,
+/// while upper 32 bits always store process id
+const ENTRY_LOW_MASK: u64 = ENTRY_BUSY - 1;
+
+/// Sequence used for building unique registration tags within the current process
+static NEXT_TAG: AtomicU32 = AtomicU32::new(0);
+
+thread_local! {
+    /// Registry entry to start searching for a free entry from
+    static ENTRY_HINT: Cell<usize> = Cell::new(
+        NEXT_TAG.fetch_add(1, Ordering::Relaxed) as usize % MAX_REGISTERED_READERS
+    );
+}
+
+/// Return process id stored within entry `tag`
+#[inline]
+fn tag_pid(tag: u64) -> u32 {
+    (tag >> 32) as u32
+}
+
+/// Registry entry describing a single reader holding a lock, padded to a separate cache line so
+/// that readers registering concurrently don't contend on the same one
+#[repr(C, align(64))]
+struct ReaderEntry {
+    /// Tag of the registration occupying the entry, storing process id of the reader
+    tag: AtomicU64,
+    /// Start time of the reader process, guarding against process id reuse
+    start_time: AtomicU64,
+    /// Pid namespace of the reader process, as process ids are only meaningful within it
+    pid_ns: AtomicU64,
+    /// Lease deadline of the reader in nanoseconds since Unix epoch, or zero without a lease
+    lease_deadline: AtomicU64,
+    /// Data instance idx locked by the reader
+    idx: AtomicU32,
+}
+
+impl ReaderEntry {
+    fn new() -> Self {
+        ReaderEntry {
+            tag: AtomicU64::new(ENTRY_FREE),
+            start_time: AtomicU64::new(0),
+            pid_ns: AtomicU64::new(0),
+            lease_deadline: AtomicU64::new(0),
+            idx: AtomicU32::new(0),
+        }
+    }
+
+    /// Return entry tag if the entry is fully registered for data instance `idx`
+    #[inline]
+    fn registered_tag(&self, idx: usize) -> Option<u64> {
+        match self.tag.load(Ordering::SeqCst) {
+            ENTRY_FREE => None,
+            tag if tag & ENTRY_BUSY != 0 => None,
+            tag => (self.idx.load(Ordering::Relaxed) as usize == idx).then_some(tag),
+        }
+    }
+
+    /// Free the entry occupied with `tag`, unless it has been freed or occupied again meanwhile
+    #[inline]
+    fn free(&self, tag: u64) -> bool {
+        self.tag
+            .compare_exchange(tag, ENTRY_FREE, Ordering::SeqCst, Ordering::Relaxed)
+            .is_ok()
+    }
+}
+
+/// Tags of registry entries whose readers were found alive by `ReaderRegistry::evict_dead`,
+/// kept for the duration of a single data instance acquisition
+pub(crate) struct LivenessCache {
+    alive: [u64; MAX_REGISTERED_READERS],
+}
+
+impl LivenessCache {
+    /// Create new cache with no entries checked
+    pub(crate) fn new() -> Self {
+        LivenessCache {
+            alive: [ENTRY_FREE; MAX_REGISTERED_READERS],
+        }
+    }
+}
+
+/// Registration of a reader within `ReaderRegistry`
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct Registration {
+    entry: usize,
+    tag: u64,
+}
+
+/// Fixed size registry of readers holding locks, stored in memory mapped state.
+///
+/// Registered readers are accounted for by the registry alone instead of readers counters,
+/// which allows writer to release locks of readers which died without unlocking, while keeping
+/// locks of live readers intact, and to honor reader leases. Readers which could not be
+/// registered, e.g. when registry is full or on platforms without process liveness checks, are
+/// accounted in readers counters instead.
+#[repr(C)]
+pub(crate) struct ReaderRegistry {
+    entries: [ReaderEntry; MAX_REGISTERED_READERS],
+}
+
+impl ReaderRegistry {
+    /// Initialize new registry with all entries free
+    pub(crate) fn new() -> Self {
+        ReaderRegistry {
+            entries: std::array::from_fn(|_| ReaderEntry::new()),
+        }
+    }
+
+    /// Register current process as a reader of data instance `idx`, holding a lease until
+    /// `lease_deadline` unless it's zero. Returns `None` if the reader can't be registered.
+    #[inline]
+    pub(crate) fn register(&self, idx: usize, lease_deadline: u64) -> Option<Registration> {
+        let process = utils::current_process()?;
+        let pid = (process.pid as u64) << 32;
+        // process id is recorded along with the claim, so that writer can free entries of
+        // readers which died before completing their registration
+        let claim = pid | ENTRY_BUSY | (process.pid_ns & ENTRY_LOW_MASK);
+        let tag = pid | (NEXT_TAG.fetch_add(1, Ordering::Relaxed) as u64 & ENTRY_LOW_MASK);
+
+        let hint = ENTRY_HINT.with(|hint| hint.get());
+        for i in 0..MAX_REGISTERED_READERS {
+            let entry_idx = (hint + i) % MAX_REGISTERED_READERS;
+            let entry = &self.entries[entry_idx];
+            if entry.tag.load(Ordering::Relaxed) != ENTRY_FREE
+                || entry
+                    .tag
+                    .compare_exchange(ENTRY_FREE, claim, Ordering::Acquire, Ordering::Relaxed)
+                    .is_err()
+            {
+                continue;
+            }
+
+            // fill in entry before publishing its tag, so that writer never observes
+            // a registered entry with fields left from its previous owner
+            entry
+                .start_time
+                .store(process.start_time, Ordering::Relaxed);
+            entry.pid_ns.store(process.pid_ns, Ordering::Relaxed);
+            entry
+                .lease_deadline
+                .store(lease_deadline, Ordering::Relaxed);
+            entry.idx.store(idx as u32, Ordering::Relaxed);
+            if entry
+                .tag
+                .compare_exchange(claim, tag, Ordering::SeqCst, Ordering::Relaxed)
+                .is_err()
+            {
+                continue;
+            }
+
+            ENTRY_HINT.with(|hint| hint.set(entry_idx));
+            return Some(Registration {
+                entry: entry_idx,
+                tag,
+            });
+        }
+
+        None
+    }
+
+    /// Unregister given `registration`. Returns `false` if it has already been evicted.
+    #[inline]
+    pub(crate) fn unregister(&self, registration: Registration) -> bool {
+        self.entries[registration.entry].free(registration.tag)
+    }
+
+    /// Indicates whether given `registration` is still present, i.e. it hasn't been evicted
+    #[inline]
+    pub(crate) fn is_registered(&self, registration: Registration) -> bool {
+        self.entries[registration.entry].tag.load(Ordering::SeqCst) == registration.tag
+    }
+
+    /// Move lease deadline of given `registration` to `lease_deadline`. Returns `false` if the
+    /// registration holds no lease or has already been evicted.
+    #[inline]
+    pub(crate) fn extend_lease(&self, registration: Registration, lease_deadline: u64) -> bool {
+        let entry = &self.entries[registration.entry];
+        if !self.is_registered(registration) || entry.lease_deadline.load(Ordering::Relaxed) == 0 {
+            return false;
+        }
+        entry.lease_deadline.store(lease_deadline, Ordering::SeqCst);
+
+        // registration might have been evicted while the lease was being extended
+        self.is_registered(registration)
+    }
+
+    /// Evict registrations of dead readers of data instance `idx`, along with entries left
+    /// half-registered by dead readers of any data instance. Returns number of evicted readers.
+    ///
+    /// Only entries holding data instance `idx` or half-registered ones are checked, each at most
+    /// once per `checked` cache, so that a writer waiting for a slow reader doesn't check all the
+    /// readers on every wake up. Readers from other pid namespaces can't be checked, so they are
+    /// never evicted.
+    pub(crate) fn evict_dead(&self, idx: usize, checked: &mut LivenessCache) -> u32 {
+        let current_pid_ns = utils::current_process().map(|process| process.pid_ns);
+        let mut evicted = 0;
+        for (entry, alive) in self.entries.iter().zip(checked.alive.iter_mut()) {
+            let tag = entry.tag.load(Ordering::SeqCst);
+            if tag == ENTRY_FREE || tag == *alive {
+                continue;
+            }
+
+            if tag & ENTRY_BUSY != 0 {
+                // only lower bits of pid namespace are recorded along with the claim
+                let Some(pid_ns) =
+                    current_pid_ns.filter(|ns| ns & ENTRY_LOW_MASK == tag & ENTRY_LOW_MASK)
+                else {
+                    continue;
+                };
+                match utils::is_process_alive(tag_pid(tag), pid_ns, None) {
+                    true => *alive = tag,
+                    false => {
+                        entry.free(tag);
+                    }
+                }
+                continue;
+            }
+
+            if entry.idx.load(Ordering::Relaxed) as usize != idx {
+                continue;
+            }
+            let start_time = entry.start_time.load(Ordering::Relaxed);
+            let pid_ns = entry.pid_ns.load(Ordering::Relaxed);
+            // reader might have unlocked in the meantime, so only evict the same registration
+            match utils::is_process_alive(tag_pid(tag), pid_ns, Some(start_time)) {
+                true => *alive = tag,
+                false => evicted += entry.free(tag) as u32,
+            }
+        }
+        evicted
+    }
+
+    /// Evict registrations of readers of data instance `idx` whose leases expired before `now`
+    /// in nanoseconds since Unix epoch. Returns number of evicted readers.
+    pub(crate) fn evict_expired(&self, idx: usize, now: u64) -> u32 {
+        let mut evicted = 0;
+        for entry in &self.entries {
+            let Some(tag) = entry.registered_tag(idx) else {
+                continue;
+            };
+            let lease_deadline = entry.lease_deadline.load(Ordering::SeqCst);
+            if lease_deadline != 0 && lease_deadline <= now && entry.free(tag) {
+                evicted += 1;
+            }
+        }
+        evicted
+    }
+
+    /// Return the earliest lease deadline of readers of data instance `idx`, or `None` if none
+    /// of them holds a lease
+    pub(crate) fn earliest_lease(&self, idx: usize) -> Option<u64> {
+        self.entries
+            .iter()
+            .filter(|entry| entry.registered_tag(idx).is_some())
+            .map(|entry| entry.lease_deadline.load(Ordering::SeqCst))
+            .filter(|&lease_deadline| lease_deadline != 0)
+            .min()
+    }
+
+    /// Return number of readers of data instance `idx`
+    pub(crate) fn count(&self, idx: usize) -> u32 {
+        self.entries
+            .iter()
+            .filter(|entry| entry.registered_tag(idx).is_some())
+            .count() as u32
+    }
+}
+
+#[cfg(all(test, target_os = "linux"))]
+pub(crate) mod tests {
+    use super::*;
+    use std::process::Command;
+
+    /// Return process id of a process which has already exited
+    pub(crate) fn dead_pid() -> u32 {
+        let mut child = Command::new("true").spawn().unwrap();
+        child.wait().unwrap();
+        child.id()
+    }
+
+    /// Replace process id stored within the entry of `registration` with `pid`
+    pub(crate) fn set_pid(registry: &ReaderRegistry, registration: &mut Registration, pid: u32) {
+        let tag = (pid as u64) << 32 | (registration.tag & ENTRY_LOW_MASK);
+        registry.entries[registration.entry]
+            .tag
+            .store(tag, Ordering::SeqCst);
+        registration.tag = tag;
+    }
+
+    #[test]
+    fn evicts_only_dead_readers() {
+        let registry = ReaderRegistry::new();
+        let live = registry.register(1, 0).unwrap();
+        let mut dead = registry.register(1, 0).unwrap();
+        let other_idx = registry.register(0, 0).unwrap();
+        set_pid(&registry, &mut dead, dead_pid());
+        assert_eq!(registry.count(1), 2);
+
+        assert_eq!(registry.evict_dead(1, &mut LivenessCache::new()), 1);
+        assert_eq!(registry.count(1), 1);
+
+        assert!(registry.unregister(live));
+        assert!(!registry.unregister(dead));
+        assert!(registry.unregister(other_idx));
+    }
+
+    #[test]
+    fn readers_of_other_pid_namespaces_are_never_evicted() {
+        let registry = ReaderRegistry::new();
+        let mut foreign = registry.register(1, 0).unwrap();
+        set_pid(&registry, &mut foreign, dead_pid());
+        registry.entries[foreign.entry]
+            .pid_ns
+            .fetch_add(1, Ordering::SeqCst);
+
+        assert_eq!(registry.evict_dead(1, &mut LivenessCache::new()), 0);
+        assert!(registry.is_registered(foreign));
+    }
+
+    #[test]
+    fn half_registered_entries_of_dead_readers_are_freed() {
+        let registry = ReaderRegistry::new();
+        let pid_ns = utils::current_process().unwrap().pid_ns;
+        let claim = |pid: u32| (pid as u64) << 32 | ENTRY_BUSY | (pid_ns & ENTRY_LOW_MASK);
+        registry.entries[0]
+            .tag
+            .store(claim(dead_pid()), Ordering::SeqCst);
+        registry.entries[1]
+            .tag
+            .store(claim(std::process::id()), Ordering::SeqCst);
+
+        assert_eq!(registry.evict_dead(0, &mut LivenessCache::new()), 0);
+        assert_eq!(registry.entries[0].tag.load(Ordering::SeqCst), ENTRY_FREE);
+        assert_ne!(registry.entries[1].tag.load(Ordering::SeqCst), ENTRY_FREE);
+    }
+
+    #[test]
+    fn live_readers_are_checked_once_per_cache() {
+        let registry = ReaderRegistry::new();
+        let reader = registry.register(1, 0).unwrap();
+        let mut checked = LivenessCache::new();
+        assert_eq!(registry.evict_dead(1, &mut checked), 0);
+
+        // reader looks dead once its start time differs, but it has already been checked
+        registry.entries[reader.entry]
+            .start_time
+            .fetch_add(1, Ordering::SeqCst);
+        assert_eq!(registry.evict_dead(1, &mut checked), 0);
+        assert!(registry.is_registered(reader));
+
+        assert_eq!(registry.evict_dead(1, &mut LivenessCache::new()), 1);
+        assert!(!registry.is_registered(reader));
+    }
+
+    #[test]
+    fn evicts_readers_with_expired_leases() {
+        let registry = ReaderRegistry::new();
+        let unleased = registry.register(1, 0).unwrap();
+        let leased = registry.register(1, 20).unwrap();
+        let _ = registry.register(1, 10).unwrap();
+        let _ = registry.register(0, 5).unwrap();
+        assert_eq!(registry.earliest_lease(1), Some(10));
+
+        assert!(!registry.extend_lease(unleased, 40));
+        assert!(registry.extend_lease(leased, 50));
+        assert_eq!(registry.evict_expired(1, 30), 1);
+        assert_eq!(registry.earliest_lease(1), Some(50));
+
+        assert_eq!(registry.evict_expired(1, 60), 1);
+        assert_eq!(registry.earliest_lease(1), None);
+        assert!(!registry.extend_lease(leased, 60));
+        assert!(registry.is_registered(unleased));
+    }
+}
@@ -0,0 +1,232 @@
+//! The `seqlock` module shares small plain-old-data values, such as counters and thresholds,
+//! without the data files and serialization used by `Synchronizer`.
+//!
+//! `SeqlockSynchronizer` stores the value within a small memory mapped file of its own along with
+//! a sequence number, which the writer keeps odd while the value is being written. Readers copy
+//! the value out optimistically and retry if the sequence number was odd or has changed in the
+//! meantime, so they never register as readers and never hold the writer back.
+use std::ffi::OsStr;
+use std::marker::PhantomData;
+use std::mem;
+use std::sync::atomic::{fence, AtomicU64, Ordering};
+
+pub use bytemuck::Pod;
+
+use crate::locks::{LockDisabled, LockMode, WriteLockStrategy};
+use crate::state::{StateContainer, StateHeader, StateLayout};
+use crate::synchronizer::SynchronizerError;
+use crate::synchronizer::SynchronizerError::*;
+use crate::utils;
+
+/// State of `SeqlockSynchronizer`, followed by the value stored in 64-bit words
+#[repr(C)]
+struct SeqlockState {
+    /// Header describing state layout
+    header: StateHeader,
+    /// Sequence number, kept odd while the value is being written
+    sequence: AtomicU64,
+    /// Fingerprint of the value type
+    fingerprint: AtomicU64,
+    /// Fencing epoch, advanced on each acquisition of fenced write lock
+    fencing_epoch: AtomicU64,
+}
+
+impl Default for SeqlockState {
+    fn default() -> Self {
+        SeqlockState {
+            header: StateHeader::new::<Self>(),
+            sequence: AtomicU64::new(0),
+            fingerprint: AtomicU64::new(0),
+            fencing_epoch: AtomicU64::new(0),
+        }
+    }
+}
+
+impl StateLayout for SeqlockState {
+    const SUFFIX: &'static str = "_seqlock";
+    const MAGIC: [u8; 8] = *b"MMAPSEQL";
+    const LAYOUT_VERSION: u32 = 1;
+
+    #[inline]
+    fn header(&self) -> &StateHeader {
+        &self.header
+    }
+
+    #[inline]
+    fn fencing(&self) -> &AtomicU64 {
+        &self.fencing_epoch
+    }
+}
+
+/// Number of read attempts spinning before yielding to other threads
+const SPIN_ATTEMPTS: usize = 64;
+/// Maximum number of read attempts, reached only if the writer died in the middle of a write
+const MAX_READ_ATTEMPTS: usize = 1 << 16;
+
+/// `SeqlockSynchronizer` shares a single plain-old-data value of type `T` between a single writer
+/// process and multiple reader processes.
+///
+/// The value is stored within the seqlock file derived from `path_prefix`, which consists of
+/// a small header followed by the value. Readers get a copy of the value, so reads are only
+/// suitable for small values.
+///
+/// Template parameters:
+///   - `T` - type of the value
+///   - `WL` - optional write locking to prevent multiple writers. (default [`LockDisabled`])
+pub struct SeqlockSynchronizer<T, WL = LockDisabled> {
+    /// Container storing seqlock state mmap along with the value
+    state_container: StateContainer<WL, SeqlockState>,
+    /// Fingerprint of type `T`
+    fingerprint: u64,
+    _value: PhantomData<T>,
+}
+
+impl<T: Pod> SeqlockSynchronizer<T> {
+    /// Create new instance of `SeqlockSynchronizer` using given `path_prefix` and default
+    /// template parameters
+    pub fn new(path_prefix: &OsStr) -> Self {
+        Self::with_params(path_prefix)
+    }
+}
+
+impl<'a, T, WL> SeqlockSynchronizer<T, WL>
+where
+    T: Pod,
+    WL: WriteLockStrategy<'a>,
+{
+    /// Create new instance of `SeqlockSynchronizer` using given `path_prefix` and template
+    /// parameters
+    pub fn with_params(path_prefix: &OsStr) -> Self {
+        SeqlockSynchronizer {
+            state_container: StateContainer::new(path_prefix).with_inline_words(value_words::<T>()),
+            fingerprint: utils::pod_type_fingerprint::<T>(),
+            _value: PhantomData,
+        }
+    }
+
+    /// Sets mode of write lock acquisition, defaulting to the one of the lock strategy `WL`.
+    pub fn with_lock_mode(mut self, lock_mode: LockMode) -> Self {
+        self.state_container.set_lock_mode(lock_mode);
+        self
+    }
+
+    /// Writes given `value`, which becomes visible to readers right away.
+    pub fn write(&'a mut self, value: &T) -> Result<(), SynchronizerError> {
+        let (state, words) = self.state_container.inline_data::<true>(true)?;
+        let sequence = &state.sequence;
+
+        // keep sequence odd while the value is being written, so that readers retry, even if
+        // the previous writer died in the middle of a write
+        let odd_sequence = (sequence.load(Ordering::Relaxed) + 1) | 1;
+        sequence.store(odd_sequence, Ordering::Relaxed);
+        fence(Ordering::Release);
+
+        state.fingerprint.store(self.fingerprint, Ordering::Relaxed);
+        let chunks = bytemuck::bytes_of(value).chunks(mem::size_of::<u64>());
+        for (word, chunk) in words.iter().zip(chunks) {
+            let mut bytes = [0; mem::size_of::<u64>()];
+            bytes[..chunk.len()].copy_from_slice(chunk);
+            word.store(u64::from_ne_bytes(bytes), Ordering::Relaxed);
+        }
+
+        sequence.store(odd_sequence + 1, Ordering::Release);
+        Ok(())
+    }
+
+    /// Reads and returns a copy of the value.
+    ///
+    /// Returns `UninitializedState` error if no value has been written yet, and `TypeMismatch`
+    /// error if the value was written as a different type.
+    pub fn read(&'a mut self) -> Result<T, SynchronizerError> {
+        let (state, words) = self.state_container.inline_data::<false>(false)?;
+        let sequence = &state.sequence;
+
+        let mut value = T::zeroed();
+        for attempt in 0..MAX_READ_ATTEMPTS {
+            let expected_sequence = sequence.load(Ordering::Acquire);
+            if expected_sequence == 0 {
+                return Err(UninitializedState);
+            }
+
+            if expected_sequence % 2 == 0 {
+                let fingerprint = state.fingerprint.load(Ordering::Relaxed);
+                let chunks = bytemuck::bytes_of_mut(&mut value).chunks_mut(mem::size_of::<u64>());
+                for (word, chunk) in words.iter().zip(chunks) {
+                    let bytes = word.load(Ordering::Relaxed).to_ne_bytes();
+                    chunk.copy_from_slice(&bytes[..chunk.len()]);
+                }
+
+                // value is consistent only if no write happened while it was being copied
+                fence(Ordering::Acquire);
+                if sequence.load(Ordering::Relaxed) == expected_sequence {
+                    return match fingerprint == self.fingerprint {
+                        true => Ok(value),
+                        false => Err(TypeMismatch),
+                    };
+                }
+            }
+
+            match attempt < SPIN_ATTEMPTS {
+                true => std::hint::spin_loop(),
+                false => std::thread::yield_now(),
+            }
+        }
+
+        Err(VersionChangedDuringRead)
+    }
+}
+
+/// Returns number of 64-bit words needed to store value of type `T`
+fn value_words<T>() -> usize {
+    mem::size_of::<T>().div_ceil(mem::size_of::<u64>())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use std::thread;
+
+    #[test]
+    fn read_returns_written_value() {
+        static PATH: &str = "/tmp/seqlock_test";
+        fs::remove_file(PATH.to_owned() + "_seqlock").unwrap_or_default();
+        let mut writer = SeqlockSynchronizer::<[u32; 5]>::new(PATH.as_ref());
+        let mut reader = SeqlockSynchronizer::<[u32; 5]>::new(PATH.as_ref());
+
+        assert!(reader.read().is_err());
+        writer.write(&[1, 2, 3, 4, 5]).unwrap();
+        let file_len = fs::metadata(PATH.to_owned() + "_seqlock").unwrap().len();
+        assert_eq!(file_len as usize, mem::size_of::<SeqlockState>() + 3 * 8);
+        assert_eq!(reader.read().unwrap(), [1, 2, 3, 4, 5]);
+        writer.write(&[6, 7, 8, 9, 10]).unwrap();
+        assert_eq!(reader.read().unwrap(), [6, 7, 8, 9, 10]);
+
+        let mut mismatched_reader = SeqlockSynchronizer::<[u16; 10]>::new(PATH.as_ref());
+        assert!(matches!(mismatched_reader.read(), Err(TypeMismatch)));
+    }
+
+    #[test]
+    fn concurrent_reads_never_observe_torn_value() {
+        static PATH: &str = "/tmp/seqlock_concurrent_test";
+        fs::remove_file(PATH.to_owned() + "_seqlock").unwrap_or_default();
+        let mut writer = SeqlockSynchronizer::<[u64; 8]>::new(PATH.as_ref());
+        writer.write(&[0; 8]).unwrap();
+
+        thread::scope(|s| {
+            for _ in 0..4 {
+                s.spawn(|| {
+                    let mut reader = SeqlockSynchronizer::<[u64; 8]>::new(PATH.as_ref());
+                    for _ in 0..10_000 {
+                        let value = reader.read().unwrap();
+                        assert!(value.iter().all(|v| *v == value[0]));
+                    }
+                });
+            }
+
+            for i in 1..10_000 {
+                writer.write(&[i; 8]).unwrap();
+            }
+        });
+    }
+}
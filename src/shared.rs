@@ -0,0 +1,458 @@
+//! The `shared` module provides `SharedReader`, allowing many threads to read data published by
+//! a `Synchronizer` concurrently through a single set of memory mappings.
+//!
+//! Unlike `Synchronizer::read`, which requires exclusive access to the `Synchronizer`,
+//! `SharedReader` hands out independent `ReadResult`s through a shared reference, so a single
+//! reader can be shared between threads, e.g. via `Arc`, and a thread can hold several results
+//! at once. Each result keeps its own reader lock, so the safety requirements of
+//! `Synchronizer::read` apply to every result separately.
+use std::ffi::{OsStr, OsString};
+use std::hash::{BuildHasherDefault, Hasher};
+use std::ops::Deref;
+use std::sync::{Arc, OnceLock};
+use std::time::Duration;
+
+use bytecheck::CheckBytes;
+use memmap2::Mmap;
+use rkyv::validation::validators::DefaultValidator;
+use rkyv::{Archive, Archived};
+use wyhash::WyHash;
+
+use crate::data::SharedDataContainer;
+use crate::guard::{OwnedReadResult, ReadGuard, ReadResult};
+use crate::instance::InstanceVersion;
+use crate::metadata::Metadata;
+use crate::state::{ReadOptions, SharedState, State};
+use crate::synchronizer::SynchronizerError::*;
+use crate::synchronizer::{archived_entity, check_header, SynchronizerError};
+use crate::utils::FingerprintCache;
+
+/// Reader guard of a locked entity along with the entity, whether data was switched and the
+/// mapping storing the entity
+type LockedEntity<'e, T, S> = (ReadGuard<S>, &'e Archived<T>, bool, Arc<Mmap>);
+
+/// `SharedReader` reads data written by a `Synchronizer` from many threads at once.
+///
+/// Template parameters:
+///   - `H` - hasher used for checksum verification, must match the one of the writer
+pub struct SharedReader<H: Hasher + Default = WyHash> {
+    /// Base path of state and data files
+    path_prefix: OsString,
+    /// State mmap shared with owned read results, mapped on first read
+    state: OnceLock<SharedState>,
+    /// Container storing data mmaps shared between threads
+    data_container: SharedDataContainer,
+    /// Hasher used for checksum verification
+    build_hasher: BuildHasherDefault<H>,
+    /// Whether to verify data checksum on every read
+    verify_checksum: bool,
+    /// Whether to check type of the data on every read
+    type_check: bool,
+    /// Fingerprint of the type read by this reader
+    fingerprints: FingerprintCache,
+    /// Maximum age of data returned by `read`
+    max_age: Option<Duration>,
+    /// Whether read results assert that they are still valid on drop in debug builds
+    assert_valid_reads: bool,
+    /// Whether readers are counted in readers counter shards of their threads
+    sharded_readers: bool,
+    /// Whether readers are recorded within the registry of readers
+    registered_readers: bool,
+}
+
+impl SharedReader {
+    /// Create new instance of `SharedReader` using given `path_prefix` and default template
+    /// parameters
+    pub fn new(path_prefix: &OsStr) -> Self {
+        Self::with_params(path_prefix)
+    }
+}
+
+impl<H: Hasher + Default> SharedReader<H> {
+    /// Create new instance of `SharedReader` using given `path_prefix` and template parameters
+    pub fn with_params(path_prefix: &OsStr) -> Self {
+        SharedReader {
+            path_prefix: path_prefix.into(),
+            state: OnceLock::new(),
+            data_container: SharedDataContainer::new(path_prefix),
+            build_hasher: BuildHasherDefault::default(),
+            verify_checksum: false,
+            type_check: true,
+            fingerprints: FingerprintCache::default(),
+            max_age: None,
+            assert_valid_reads: false,
+            sharded_readers: false,
+            registered_readers: false,
+        }
+    }
+
+    /// Enables or disables verification of data checksum on every read, the same way as
+    /// `Synchronizer::with_checksum_verification` does.
+    pub fn with_checksum_verification(mut self, enabled: bool) -> Self {
+        self.verify_checksum = enabled;
+        self
+    }
+
+    /// Enables or disables check of the type of data on every read, the same way as
+    /// `Synchronizer::with_type_check` does.
+    pub fn with_type_check(mut self, enabled: bool) -> Self {
+        self.type_check = enabled;
+        self
+    }
+
+    /// Sets maximum age of data returned by `read`, the same way as `Synchronizer::with_max_age`
+    /// does.
+    pub fn with_max_age(mut self, max_age: Duration) -> Self {
+        self.max_age = Some(max_age);
+        self
+    }
+
+    /// Enables assertion that `ReadResult`s are still valid when dropped, in debug builds only,
+    /// the same way as `Synchronizer::with_validity_assertion` does.
+    pub fn with_validity_assertion(mut self, enabled: bool) -> Self {
+        self.assert_valid_reads = enabled;
+        self
+    }
+
+    /// Enables counting of readers in shards of readers counters picked per reader thread, the
+    /// same way as `Synchronizer::with_sharded_readers` does.
+    pub fn with_sharded_readers(mut self, enabled: bool) -> Self {
+        self.sharded_readers = enabled;
+        self
+    }
+
+    /// Enables recording of readers within the registry of readers, the same way as
+    /// `Synchronizer::with_registered_readers` does.
+    pub fn with_registered_readers(mut self, enabled: bool) -> Self {
+        self.registered_readers = enabled;
+        self
+    }
+
+    /// Reads and returns an `entity` struct from mapped memory wrapped in `ReadGuard`, the same
+    /// way as `Synchronizer::read` does.
+    ///
+    /// `ReadResult::is_switched` of the result indicates whether data was switched since the
+    /// previous read of the current thread through this reader, so reads of other threads never
+    /// affect it.
+    ///
+    /// # Safety
+    ///
+    /// Same as `Synchronizer::read`.
+    pub unsafe fn read<T>(&self, check_bytes: bool) -> Result<ReadResult<'_, T>, SynchronizerError>
+    where
+        T: Archive,
+        T::Archived: for<'b> CheckBytes<DefaultValidator<'b>>,
+    {
+        self.read_entity::<T>(check_bytes, None, false)
+    }
+
+    /// Reads and returns an `entity` struct from mapped memory wrapped in `ReadGuard`, holding
+    /// a lease for given `lease` duration, the same way as `Synchronizer::read_with_lease` does.
+    ///
+    /// # Safety
+    ///
+    /// Same as `Synchronizer::read_with_lease`.
+    pub unsafe fn read_with_lease<T>(
+        &self,
+        check_bytes: bool,
+        lease: Duration,
+    ) -> Result<ReadResult<'_, T>, SynchronizerError>
+    where
+        T: Archive,
+        T::Archived: for<'b> CheckBytes<DefaultValidator<'b>>,
+    {
+        self.read_entity::<T>(check_bytes, Some(lease), false)
+    }
+
+    /// Reads and returns an `entity` struct from mapped memory wrapped in `OwnedReadResult`,
+    /// which doesn't borrow the reader.
+    ///
+    /// The result holds the reader lock along with the state and data mappings until dropped,
+    /// so it can be stored in a struct or moved to another thread, e.g. to carry a consistent
+    /// snapshot of data across await points of an asynchronous task.
+    ///
+    /// # Safety
+    ///
+    /// Same as `read`.
+    pub unsafe fn read_owned<T>(
+        &self,
+        check_bytes: bool,
+    ) -> Result<OwnedReadResult<T>, SynchronizerError>
+    where
+        T: Archive,
+        T::Archived: for<'b> CheckBytes<DefaultValidator<'b>>,
+    {
+        self.read_owned_entity::<T>(check_bytes, None)
+    }
+
+    /// Reads and returns an `entity` struct from mapped memory wrapped in `OwnedReadResult`,
+    /// holding a lease for given `lease` duration the same way as `read_with_lease` does.
+    ///
+    /// # Safety
+    ///
+    /// Same as `read_with_lease`.
+    pub unsafe fn read_owned_with_lease<T>(
+        &self,
+        check_bytes: bool,
+        lease: Duration,
+    ) -> Result<OwnedReadResult<T>, SynchronizerError>
+    where
+        T: Archive,
+        T::Archived: for<'b> CheckBytes<DefaultValidator<'b>>,
+    {
+        self.read_owned_entity::<T>(check_bytes, Some(lease))
+    }
+
+    /// Safely reads and returns an `entity` struct from mapped memory wrapped in `ReadGuard`
+    /// once strict mode is enabled by the writer, the same way as `Synchronizer::safe_read` does.
+    pub fn safe_read<T>(&self) -> Result<ReadResult<'_, T>, SynchronizerError>
+    where
+        T: Archive,
+        T::Archived: for<'b> CheckBytes<DefaultValidator<'b>>,
+    {
+        // SAFETY: readers are never reset in strict mode and bytes are validated
+        unsafe { self.read_entity::<T>(true, None, true) }
+    }
+
+    /// Returns current `InstanceVersion` stored within the state, useful for detecting
+    /// whether synchronized `entity` has changed.
+    pub fn version(&self) -> Result<InstanceVersion, SynchronizerError> {
+        self.state()?.version()
+    }
+
+    /// Returns publication generation of current version stored within the state.
+    pub fn generation(&self) -> Result<u64, SynchronizerError> {
+        Ok(self.state()?.publication())
+    }
+
+    /// Returns publication `Metadata` of current version stored within the state.
+    pub fn metadata(&self) -> Result<Metadata, SynchronizerError> {
+        self.state()?.metadata()
+    }
+
+    /// Reads an `entity` struct from mapped memory holding optional `lease`, only if strict mode
+    /// is enabled when `strict_only` is set.
+    ///
+    /// # Safety
+    ///
+    /// Same as `read`, unless `strict_only` and `check_bytes` are both set.
+    unsafe fn read_entity<T>(
+        &self,
+        check_bytes: bool,
+        lease: Option<Duration>,
+        strict_only: bool,
+    ) -> Result<ReadResult<'_, T>, SynchronizerError>
+    where
+        T: Archive,
+        T::Archived: for<'b> CheckBytes<DefaultValidator<'b>>,
+    {
+        let state: &State = self.state()?;
+        let (guard, entity, switched, mmap) =
+            self.lock_entity::<T, _>(state, check_bytes, lease, strict_only)?;
+        Ok(ReadResult::with_mmap(guard, entity, switched, mmap))
+    }
+
+    /// Reads an `entity` struct from mapped memory holding optional `lease` into a result sharing
+    /// the mappings.
+    ///
+    /// # Safety
+    ///
+    /// Same as `read`.
+    unsafe fn read_owned_entity<T>(
+        &self,
+        check_bytes: bool,
+        lease: Option<Duration>,
+    ) -> Result<OwnedReadResult<T>, SynchronizerError>
+    where
+        T: Archive,
+        T::Archived: for<'b> CheckBytes<DefaultValidator<'b>>,
+    {
+        let state = self.state()?.clone();
+        let (guard, entity, switched, mmap) =
+            self.lock_entity::<T, _>(state, check_bytes, lease, false)?;
+        Ok(OwnedReadResult::new(guard, entity, switched, mmap))
+    }
+
+    /// Locks current version of given `state` and fetches an `entity` struct of the version
+    /// from shared mapping, which must be kept alive for as long as the entity is used.
+    ///
+    /// # Safety
+    ///
+    /// Same as `read_entity`, additionally the lifetime of the entity is unbounded.
+    unsafe fn lock_entity<'e, T, S>(
+        &self,
+        state: S,
+        check_bytes: bool,
+        lease: Option<Duration>,
+        strict_only: bool,
+    ) -> Result<LockedEntity<'e, T, S>, SynchronizerError>
+    where
+        T: Archive,
+        T::Archived: for<'b> CheckBytes<DefaultValidator<'b>>,
+        S: Deref<Target = State>,
+    {
+        if strict_only && !state.is_strict() {
+            return Err(StrictModeDisabled);
+        }
+
+        // create and lock state guard for reading current version
+        let options = ReadOptions {
+            lease,
+            sharded: self.sharded_readers,
+            registered: self.registered_readers,
+        };
+        let guard = ReadGuard::new(state, &options, self.assert_valid_reads)?;
+        let fingerprints = self.type_check.then_some(&self.fingerprints);
+        check_header::<T>(&guard, fingerprints, self.max_age)?;
+
+        // fetch data for current version from shared mapping, which is moved into the result
+        // along with the entity referring to it
+        let (mmap, switched) = self
+            .data_container
+            .data(guard.version(), guard.generation())?;
+        let data = &*(&mmap[..guard.version().size()] as *const [u8]);
+        let hasher = self.verify_checksum.then_some(&self.build_hasher);
+        let entity = archived_entity::<T, H>(&guard, data, hasher, check_bytes)?;
+
+        Ok((guard, entity, switched, mmap))
+    }
+
+    /// Fetch state from memory mapped file, mapping it on first access
+    fn state(&self) -> Result<&SharedState, SynchronizerError> {
+        if let Some(state) = self.state.get() {
+            return Ok(state);
+        }
+
+        // several threads might map the state concurrently, in which case only the mapping
+        // made first is kept
+        let _ = self.state.set(SharedState::map(&self.path_prefix)?);
+        Ok(self.state.get().unwrap())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::synchronizer::Synchronizer;
+    use rkyv::{Deserialize, Serialize};
+    use std::fs;
+    use std::thread;
+
+    #[derive(Archive, Deserialize, Serialize, Debug, PartialEq)]
+    #[archive_attr(derive(CheckBytes))]
+    struct UniformEntity {
+        values: Vec<u64>,
+    }
+
+    #[test]
+    fn shared_reader_is_send_and_sync() {
+        fn assert_send_sync<T: Send + Sync>() {}
+        assert_send_sync::<SharedReader>();
+    }
+
+    #[test]
+    fn owned_read_result_outlives_borrow_of_reader() {
+        static PATH: &str = "/tmp/shared_reader_owned_test";
+        fs::remove_file(PATH.to_owned() + "_state").unwrap_or_default();
+        let mut writer = Synchronizer::new(PATH.as_ref());
+        writer
+            .write(
+                &UniformEntity { values: vec![1; 8] },
+                Duration::from_secs(1),
+            )
+            .unwrap();
+
+        let reader = SharedReader::new(PATH.as_ref());
+        let res = unsafe { reader.read_owned::<UniformEntity>(true).unwrap() };
+        drop(reader);
+
+        // result keeps the data file locked from another thread, even once the reader is gone
+        let res = thread::spawn(move || {
+            assert_eq!(res.values.as_slice(), [1; 8]);
+            res
+        })
+        .join()
+        .unwrap();
+        assert!(writer
+            .try_write(&UniformEntity { values: vec![2; 8] })
+            .is_ok());
+        assert!(matches!(
+            writer.try_write(&UniformEntity { values: vec![3; 8] }),
+            Err(SynchronizerError::ReadersStillActive { readers: 1 })
+        ));
+
+        drop(res);
+        assert!(writer
+            .try_write(&UniformEntity { values: vec![3; 8] })
+            .is_ok());
+    }
+
+    #[test]
+    fn switches_are_tracked_per_reader_thread() {
+        static PATH: &str = "/tmp/shared_reader_switched_test";
+        fs::remove_file(PATH.to_owned() + "_state").unwrap_or_default();
+        let mut writer = Synchronizer::new(PATH.as_ref());
+        let reader = SharedReader::new(PATH.as_ref());
+        let is_switched = || unsafe { reader.read::<UniformEntity>(true).unwrap().is_switched() };
+        let is_switched_in_thread = || thread::scope(|s| s.spawn(is_switched).join().unwrap());
+
+        writer
+            .write(
+                &UniformEntity { values: vec![1; 8] },
+                Duration::from_secs(1),
+            )
+            .unwrap();
+        assert!(is_switched());
+        assert!(!is_switched());
+
+        // another thread observes the switch itself, while its read affects no other thread
+        writer
+            .write(
+                &UniformEntity { values: vec![2; 8] },
+                Duration::from_secs(1),
+            )
+            .unwrap();
+        assert!(is_switched_in_thread());
+        assert!(is_switched());
+        assert!(!is_switched());
+        assert!(is_switched_in_thread());
+    }
+
+    #[test]
+    fn threads_read_concurrently_through_shared_reader() {
+        static PATH: &str = "/tmp/shared_reader_test";
+        fs::remove_file(PATH.to_owned() + "_state").unwrap_or_default();
+        let mut writer = Synchronizer::new(PATH.as_ref());
+        let reader = SharedReader::new(PATH.as_ref()).with_checksum_verification(true);
+        writer
+            .write(
+                &UniformEntity { values: vec![0; 8] },
+                Duration::from_secs(1),
+            )
+            .unwrap();
+
+        thread::scope(|s| {
+            for _ in 0..4 {
+                s.spawn(|| {
+                    for _ in 0..1000 {
+                        // the same thread holds several results at once
+                        let first = unsafe { reader.read::<UniformEntity>(true).unwrap() };
+                        let second = unsafe { reader.read::<UniformEntity>(true).unwrap() };
+                        for res in [&first, &second] {
+                            assert!(res.values.iter().all(|v| *v == res.values[0]));
+                        }
+                        assert!(first.generation() <= second.generation());
+                    }
+                });
+            }
+
+            // growing data forces readers to remap data files while others still use them
+            for i in 1..100 {
+                let entity = UniformEntity {
+                    values: vec![i; 8 + i as usize],
+                };
+                writer.write(&entity, Duration::from_secs(1)).unwrap();
+            }
+        });
+    }
+}
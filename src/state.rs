@@ -1,37 +1,346 @@
 use memmap2::MmapMut;
 use std::ffi::{OsStr, OsString};
 use std::fs::OpenOptions;
-use std::ops::{Add, DerefMut};
-use std::sync::atomic::{AtomicU32, AtomicU64, Ordering};
-use std::time::{Duration, Instant};
-use std::{mem, thread};
+use std::marker::PhantomData;
+use std::mem;
+use std::ops::{Add, Deref, DerefMut};
+use std::sync::atomic::{AtomicBool, AtomicU32, AtomicU64, AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 
 #[cfg(unix)]
 use std::os::unix::fs::OpenOptionsExt;
 
-use crate::instance::InstanceVersion;
-use crate::locks::WriteLockStrategy;
-use crate::synchronizer::SynchronizerError;
+use crate::futex::{self, Waiter};
+use crate::instance::{InstanceVersion, MAX_DATA_INSTANCES};
+use crate::locks::{LockDisabled, LockMode, StateMmap, WriteLockStrategy};
+use crate::metadata::Metadata;
+use crate::registry::{LivenessCache, ReaderRegistry, Registration};
 use crate::synchronizer::SynchronizerError::*;
+use crate::synchronizer::{ActiveReadersPolicy, SynchronizerError};
 use crate::utils;
 
-const STATE_SIZE: usize = mem::size_of::<State>();
+/// Magic bytes identifying state files
+const STATE_MAGIC: [u8; 8] = *b"MMAPSYNC";
+/// Magic bytes claiming initialization of a state file of any layout, replaced by the magic bytes
+/// of the layout once initialized
+const INITIALIZING_MAGIC: [u8; 8] = *b"MMAPINIT";
+/// Maximum period to wait for another writer initializing the same state file
+const INITIALIZATION_TIMEOUT: Duration = Duration::from_secs(1);
+/// Version of `State` layout, must be incremented on every change of the layout
+const STATE_LAYOUT_VERSION: u32 = 1;
+/// Endianness of the current platform as recorded in `StateHeader`
+const STATE_ENDIANNESS: u8 = if cfg!(target_endian = "little") { 1 } else { 2 };
+/// Pointer width of the current platform in bytes as recorded in `StateHeader`
+const STATE_POINTER_WIDTH: u8 = mem::size_of::<usize>() as u8;
+
+/// Maximum number of attempts to lock current version for reading while writer keeps switching it
+const MAX_RLOCK_ATTEMPTS: usize = 16;
+
+#[cfg(test)]
+thread_local! {
+    /// Hook called between loading the version and registering as a reader, used by tests to
+    /// let writer switch versions within that window. Hook is set per reader thread, so that it
+    /// never affects tests running in parallel.
+    pub(crate) static RLOCK_HOOK: std::cell::RefCell<Option<Box<dyn FnMut()>>> =
+        const { std::cell::RefCell::new(None) };
+
+    /// Hook called once right before a fenced writer publishes its version, used by tests to
+    /// let another writer take over within that window
+    pub(crate) static SWITCH_HOOK: std::cell::RefCell<Option<Box<dyn FnOnce()>>> =
+        const { std::cell::RefCell::new(None) };
+}
+
+/// Bits of `idx_readers` entry storing the number of readers
+const READERS_COUNT_MASK: u64 = u32::MAX as u64;
+/// Shift of `idx_readers` entry bits storing the reset generation
+const READERS_GENERATION_SHIFT: u32 = 32;
+
+/// Number of shards striping readers counter of each data instance
+const READER_SHARDS: usize = 16;
+
+/// Sequence used for assigning readers counter shards to threads of the current process
+static NEXT_READER_SHARD: AtomicUsize = AtomicUsize::new(0);
+
+thread_local! {
+    /// Readers counter shard used by the current thread in sharded mode, spreading threads of
+    /// different processes as well
+    static READER_SHARD: usize = (std::process::id() as usize)
+        .wrapping_add(NEXT_READER_SHARD.fetch_add(1, Ordering::Relaxed))
+        % READER_SHARDS;
+}
+
+/// Shard of readers counter padded to a cache line, so that readers counted in different shards
+/// don't contend for the same cache line
+#[repr(C, align(64))]
+struct ReaderShard(AtomicU64);
+
+/// Returns deadline of a reader `lease` starting now in nanoseconds since Unix epoch, as stored
+/// within the registry
+fn lease_deadline(lease: Duration) -> u64 {
+    utils::unix_nanos(SystemTime::now() + lease)
+}
+
+/// Layout of a memory mapped state file, which starts with `StateHeader` and is followed by
+/// optional inline data
+pub(crate) trait StateLayout: Default {
+    /// Suffix appended to path prefix to derive state file path
+    const SUFFIX: &'static str;
+    /// Magic bytes identifying state files of this layout
+    const MAGIC: [u8; 8];
+    /// Version of the layout, must be incremented on every change of the layout
+    const LAYOUT_VERSION: u32;
+
+    /// Return header of the state
+    fn header(&self) -> &StateHeader;
+
+    /// Return fencing epoch, advanced on each acquisition of fenced write lock
+    fn fencing(&self) -> &AtomicU64;
+
+    /// Advance fencing epoch on acquisition of fenced write lock, returns the new epoch
+    #[inline]
+    fn advance_fencing_epoch(&self) -> u64 {
+        self.fencing().fetch_add(1, Ordering::SeqCst) + 1
+    }
+
+    /// Ensure that writer holding given fencing `epoch` is still allowed to publish, i.e. nobody
+    /// acquired fenced write lock since then
+    #[inline]
+    fn check_fencing_epoch(&self, epoch: Option<u64>) -> Result<(), SynchronizerError> {
+        match (epoch, self.fencing().load(Ordering::SeqCst)) {
+            (Some(epoch), current) if epoch != current => Err(FencedOut { epoch, current }),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Header describing state file layout, allowing to reject incompatible state files
+#[repr(C)]
+pub(crate) struct StateHeader {
+    /// Magic bytes identifying state file, written last during state initialization. Must be the
+    /// first word of every state layout, since initialization is claimed by a CAS on it.
+    magic: AtomicU64,
+    /// Version of `State` layout
+    layout_version: u32,
+    /// Endianness of the platform which initialized state
+    endianness: u8,
+    /// Pointer width of the platform which initialized state
+    pointer_width: u8,
+}
+
+impl StateHeader {
+    pub(crate) fn new<S: StateLayout>() -> Self {
+        StateHeader {
+            magic: AtomicU64::new(0),
+            layout_version: S::LAYOUT_VERSION,
+            endianness: STATE_ENDIANNESS,
+            pointer_width: STATE_POINTER_WIDTH,
+        }
+    }
+
+    /// Check whether state described by the header is compatible with the current one of layout
+    /// `S`
+    fn validate<S: StateLayout>(&self) -> Result<(), SynchronizerError> {
+        match self.magic.load(Ordering::SeqCst).to_ne_bytes() {
+            magic if magic == S::MAGIC => {}
+            [0, 0, 0, 0, 0, 0, 0, 0] | INITIALIZING_MAGIC => return Err(UninitializedState),
+            _ => return Err(InvalidStateFile),
+        }
+
+        // endianness and pointer width are checked first, since they affect layout version
+        if self.endianness != STATE_ENDIANNESS || self.pointer_width != STATE_POINTER_WIDTH {
+            return Err(IncompatibleStatePlatform);
+        }
+
+        match self.layout_version {
+            version if version == S::LAYOUT_VERSION => Ok(()),
+            found => Err(IncompatibleStateLayout {
+                expected: S::LAYOUT_VERSION,
+                found,
+            }),
+        }
+    }
+}
+
+/// Header describing data instance published into one of the data files
+#[repr(C)]
+pub(crate) struct DataHeader {
+    /// Publication generation of the data instance
+    generation: AtomicU64,
+    /// Full-width checksum of the data instance
+    checksum: AtomicU64,
+    /// Fingerprint of the archived type of the data instance
+    type_fingerprint: AtomicU64,
+    /// Publication time of the data instance in nanoseconds since Unix epoch
+    published_at: AtomicU64,
+    /// Hash of the hostname of the writer which published the data instance
+    hostname_hash: AtomicU64,
+    /// Process id of the writer which published the data instance
+    writer_pid: AtomicU32,
+}
+
+impl DataHeader {
+    fn new() -> Self {
+        DataHeader {
+            generation: AtomicU64::new(0),
+            checksum: AtomicU64::new(0),
+            type_fingerprint: AtomicU64::new(0),
+            published_at: AtomicU64::new(0),
+            hostname_hash: AtomicU64::new(0),
+            writer_pid: AtomicU32::new(0),
+        }
+    }
+
+    /// Returns full-width checksum of the data instance
+    #[inline]
+    pub(crate) fn checksum(&self) -> u64 {
+        self.checksum.load(Ordering::SeqCst)
+    }
+
+    /// Returns fingerprint of the archived type of the data instance
+    #[inline]
+    pub(crate) fn type_fingerprint(&self) -> u64 {
+        self.type_fingerprint.load(Ordering::SeqCst)
+    }
+
+    /// Returns publication metadata of the data instance
+    #[inline]
+    pub(crate) fn metadata(&self) -> Metadata {
+        Metadata::from_raw(
+            self.published_at.load(Ordering::SeqCst),
+            self.writer_pid.load(Ordering::SeqCst),
+            self.hostname_hash.load(Ordering::SeqCst),
+        )
+    }
+}
 
 /// State stored in memory for synchronization using atomics
 #[repr(C)]
 pub(crate) struct State<const SD: usize = 1_000_000_000> {
+    /// Header describing state layout
+    header: StateHeader,
     /// Current data instance version
     version: AtomicU64,
-    /// Current number of readers for each data instance
-    idx_readers: [AtomicU32; 2],
+    /// Monotonically increasing publication generation, incremented on each version switch and
+    /// on each acquisition of fenced write lock
+    generation: AtomicU64,
+    /// Wall-clock time of the last writer heartbeat in nanoseconds since Unix epoch
+    heartbeat: AtomicU64,
+    /// Fencing epoch, advanced on each acquisition of fenced write lock
+    fencing_epoch: AtomicU64,
+    /// Current number of unregistered readers (lower 32 bits) and reset generation (upper 32
+    /// bits) for each data instance. Reset generation serves as an eviction epoch of the data
+    /// instance, invalidating all the unregistered reader locks taken before the reset.
+    ///
+    /// Readers are counted in the first shard unless they opt into sharded mode, while writer sums
+    /// up all the shards and advances reset generation of all of them together.
+    idx_readers: [[ReaderShard; READER_SHARDS]; MAX_DATA_INSTANCES],
+    /// Number of reader unlocks ignored since the last write due to a reset of readers counter
+    /// or an eviction from the registry
+    stale_unlocks: AtomicU32,
+    /// Registry of readers holding locks, which are not counted in `idx_readers`
+    registry: ReaderRegistry,
+    /// Non-zero while writer waits for readers to leave, so that the last one wakes it up
+    writer_waiting: AtomicU32,
+    /// Incremented whenever a registered reader leaves while writer waits, waking it up
+    unregistrations: AtomicU32,
+    /// Incremented once a new version becomes visible, waking up readers waiting for changes
+    switches: AtomicU32,
+    /// Non-zero once strict mode is enabled, in which live readers are never reset by any writer
+    strict: AtomicU32,
+    /// Headers of data instances stored in each data file
+    data_headers: [DataHeader; MAX_DATA_INSTANCES],
+}
+
+/// Options of data instance acquisition via `State::acquire_next_idx`
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct AcquireOptions {
+    /// Number of data instances written in turns
+    pub(crate) num_instances: usize,
+    /// Maximum period to wait for readers of the oldest data instance to leave
+    pub(crate) grace_duration: Duration,
+    /// Maximum duration of a single sleep while waiting for readers to leave
+    pub(crate) max_sleep_duration: Duration,
+    /// Handling of readers still active once grace period expires
+    pub(crate) policy: ActiveReadersPolicy,
+    /// Whether to enable strict mode, which is recorded within the state for all the writers
+    pub(crate) strict: bool,
+}
+
+/// Options of reader locks taken via `State::rlock`
+#[derive(Clone, Copy, Debug, Default)]
+pub(crate) struct ReadOptions {
+    /// Lease of the reader, honored by writer instead of its grace period
+    pub(crate) lease: Option<Duration>,
+    /// Whether to count the reader in the readers counter shard of the current thread instead
+    /// of the first shard
+    pub(crate) sharded: bool,
+    /// Whether to record the reader within the registry of readers instead of counting it,
+    /// which is always the case for readers holding a lease
+    pub(crate) registered: bool,
+}
+
+/// Data instance acquired for writing via `State::acquire_next_idx`
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) struct Acquisition {
+    /// Index of acquired data instance
+    pub(crate) idx: usize,
+    /// Whether readers counter of the data instance was reset
+    pub(crate) reset: bool,
+    /// Number of readers evicted from the data instance, either dead, reset or with expired lease
+    pub(crate) evicted: u32,
+}
+
+/// Accounting of a reader lock taken via `State::rlock`
+#[derive(Clone, Copy, Debug)]
+enum ReadLockKind {
+    /// Reader counted in readers counter `shard` locked with reset `generation`
+    Counted { shard: usize, generation: u32 },
+    /// Reader recorded within the registry of readers
+    Registered(Registration),
+}
+
+/// Reader lock of a data instance version acquired via `State::rlock`
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct ReadLock {
+    version: InstanceVersion,
+    kind: ReadLockKind,
+    publication: u64,
+}
+
+impl ReadLock {
+    /// Returns locked version
+    #[inline]
+    pub(crate) fn version(&self) -> InstanceVersion {
+        self.version
+    }
+
+    /// Returns publication generation of locked version
+    #[inline]
+    pub(crate) fn publication(&self) -> u64 {
+        self.publication
+    }
 }
 
 impl State {
     /// Initialize new state with zero values
     pub(crate) fn new() -> State {
         State {
+            header: StateHeader::new::<Self>(),
             version: AtomicU64::new(0),
-            idx_readers: [AtomicU32::new(0), AtomicU32::new(0)],
+            generation: AtomicU64::new(0),
+            heartbeat: AtomicU64::new(0),
+            fencing_epoch: AtomicU64::new(0),
+            idx_readers: std::array::from_fn(|_| {
+                std::array::from_fn(|_| ReaderShard(AtomicU64::new(0)))
+            }),
+            stale_unlocks: AtomicU32::new(0),
+            registry: ReaderRegistry::new(),
+            writer_waiting: AtomicU32::new(0),
+            unregistrations: AtomicU32::new(0),
+            switches: AtomicU32::new(0),
+            strict: AtomicU32::new(0),
+            data_headers: std::array::from_fn(|_| DataHeader::new()),
         }
     }
 
@@ -41,57 +350,550 @@ impl State {
         self.version.load(Ordering::SeqCst).try_into()
     }
 
-    /// Locks given `version` of the state for reading
+    /// Return state's current publication generation
+    #[inline]
+    pub(crate) fn generation(&self) -> u64 {
+        self.generation.load(Ordering::SeqCst)
+    }
+
+    /// Return publication generation of state's current version, or zero if there is none yet.
+    ///
+    /// Generation is loaded again if the writer switched versions while it was being loaded.
+    #[inline]
+    pub(crate) fn publication(&self) -> u64 {
+        loop {
+            let version = self.version.load(Ordering::SeqCst);
+            let generation = match InstanceVersion::try_from(version) {
+                Ok(version) => self.data_headers[version.idx()]
+                    .generation
+                    .load(Ordering::SeqCst),
+                Err(_) => 0,
+            };
+            if self.version.load(Ordering::SeqCst) == version {
+                return generation;
+            }
+        }
+    }
+
+    /// Return number of version switches, wrapping around on overflow
+    #[cfg(feature = "tokio")]
+    #[inline]
+    pub(crate) fn switches(&self) -> u32 {
+        self.switches.load(Ordering::SeqCst)
+    }
+
+    /// Record writer heartbeat at the current wall-clock time
+    #[inline]
+    pub(crate) fn heartbeat(&self) {
+        self.heartbeat
+            .store(utils::unix_nanos(SystemTime::now()), Ordering::SeqCst);
+    }
+
+    /// Return wall-clock time of the last writer heartbeat, or `None` if there was none yet
+    #[inline]
+    pub(crate) fn last_heartbeat(&self) -> Option<SystemTime> {
+        match self.heartbeat.load(Ordering::SeqCst) {
+            0 => None,
+            nanos => Some(UNIX_EPOCH + Duration::from_nanos(nanos)),
+        }
+    }
+
+    /// Return state's current fencing epoch
+    #[inline]
+    pub(crate) fn fencing_epoch(&self) -> u64 {
+        self.fencing_epoch.load(Ordering::SeqCst)
+    }
+
+    /// Indicates whether strict mode is enabled, i.e. no writer ever resets readers
+    #[inline]
+    pub(crate) fn is_strict(&self) -> bool {
+        self.strict.load(Ordering::SeqCst) != 0
+    }
+
+    /// Locks given `version` of the state for reading according to given `options`.
+    ///
+    /// Readers asking to be registered are counted in readers counter if they can't be
+    /// registered.
+    #[inline]
+    pub(crate) fn rlock(&self, version: InstanceVersion, options: &ReadOptions) -> ReadLock {
+        let registration = match options.registered || options.lease.is_some() {
+            true => self
+                .registry
+                .register(version.idx(), options.lease.map_or(0, lease_deadline)),
+            false => None,
+        };
+        let kind = match registration {
+            Some(registration) => ReadLockKind::Registered(registration),
+            None => {
+                let shard = match options.sharded {
+                    true => READER_SHARD.with(|shard| *shard),
+                    false => 0,
+                };
+                let readers = self.idx_readers[version.idx()][shard]
+                    .0
+                    .fetch_add(1, Ordering::SeqCst);
+                let generation = (readers >> READERS_GENERATION_SHIFT) as u32;
+                ReadLockKind::Counted { shard, generation }
+            }
+        };
+        ReadLock {
+            version,
+            kind,
+            publication: self.data_headers[version.idx()]
+                .generation
+                .load(Ordering::SeqCst),
+        }
+    }
+
+    /// Locks current version of the state for reading.
+    ///
+    /// Version is loaded again after registering as a reader, because writer might have switched
+    /// versions in between and started overwriting data instance of the loaded version while it
+    /// still had no readers. In such case reader is unregistered and the whole sequence is retried.
+    /// Publication generation is checked as well, since the writer might have switched back to
+    /// the very same version in between.
+    ///
+    /// Writer waits for registered readers until their leases expire instead of its grace period,
+    /// or for as long as they are alive if they hold no lease.
     #[inline]
-    pub(crate) fn rlock(&mut self, version: InstanceVersion) {
-        self.idx_readers[version.idx()].fetch_add(1, Ordering::SeqCst);
+    pub(crate) fn rlock_current(
+        &self,
+        options: &ReadOptions,
+    ) -> Result<ReadLock, SynchronizerError> {
+        for _ in 0..MAX_RLOCK_ATTEMPTS {
+            let generation = self.generation();
+            let version = self.version()?;
+            #[cfg(test)]
+            RLOCK_HOOK.with(|hook| hook.borrow_mut().as_mut().map(|hook| hook()));
+            let lock = self.rlock(version, options);
+            if self.generation() == generation && self.version()? == version {
+                return Ok(lock);
+            }
+            self.runlock(lock);
+        }
+
+        Err(VersionChangedDuringRead)
     }
 
-    /// Acquire next `idx` of the state for writing
+    /// Acquire next `idx` of the state for writing according to given `options`.
+    ///
+    /// Any data instance other than the current one without readers is acquired right away,
+    /// starting from the one following the current one, i.e. the oldest. If all of them are
+    /// still being read, writer sleeps until the last reader of the oldest one leaves and wakes it
+    /// up, but never longer than `max_sleep_duration` at once, so that readers which died without
+    /// unlocking are noticed. Registered readers which died are evicted right away, even in
+    /// strict mode. Only registered readers of data instances without unregistered readers are
+    /// checked for liveness, each at most once per `max_sleep_duration`.
+    ///
+    /// Readers of the oldest data instance are handled according to `ActiveReadersPolicy`, except
+    /// that live readers are never reset nor evicted in strict mode, failing the acquisition
+    /// instead. With the
+    /// `Reset` policy, unregistered readers are reset once grace period expires, and registered
+    /// readers are evicted once their leases expire, while registered readers known to be alive
+    /// without a lease are waited for. With the `Fail` policy, acquisition fails as soon as grace
+    /// period expires, regardless of leases.
     #[inline]
     pub(crate) fn acquire_next_idx(
         &self,
-        grace_duration: Duration,
-        sleep_duration: Duration,
-    ) -> (usize, bool) {
-        // calculate `next_idx` to acquire, in case of uninitialized version use 0
-        let next_idx = match InstanceVersion::try_from(self.version.load(Ordering::SeqCst)) {
-            Ok(version) => (version.idx() + 1) % 2,
-            Err(_) => 0,
+        options: &AcquireOptions,
+    ) -> Result<Acquisition, SynchronizerError> {
+        let num_instances = options.num_instances;
+
+        // strict mode can't be disabled once enabled, as readers rely on it for memory safety
+        if options.strict {
+            self.strict.store(1, Ordering::SeqCst);
+        }
+        let strict = self.is_strict();
+        let policy = match options.policy {
+            ActiveReadersPolicy::Reset if strict => ActiveReadersPolicy::Fail,
+            policy => policy,
         };
 
-        // check number of readers using `next_idx`
-        let num_readers = &self.idx_readers[next_idx];
+        // calculate `next_idx` following the current one, in case of uninitialized version use 0
+        let current_idx = InstanceVersion::try_from(self.version.load(Ordering::SeqCst))
+            .ok()
+            .map(|version| version.idx());
+        let next_idx = current_idx.map_or(0, |idx| (idx + 1) % num_instances);
 
-        // wait until either no more readers left for `next_idx` or grace period has expired
-        let grace_expiring_at = Instant::now().add(grace_duration);
+        // wait until either no more readers left for any data instance or grace period has
+        // expired for `next_idx`
+        let grace_expiring_at = Instant::now().add(options.grace_duration);
+        let mut evicted = 0;
         let mut reset = false;
-        while num_readers.load(Ordering::SeqCst) > 0 {
-            // we should reach here only when one of the readers dies without decrement
-            if Instant::now().gt(&grace_expiring_at) {
-                // reset number of readers after expired grace period
-                num_readers.store(0, Ordering::SeqCst);
-                reset = true;
-                break;
+        let mut waiter = Waiter::new();
+        let mut checked = LivenessCache::new();
+        let mut checked_at = Instant::now();
+        loop {
+            // liveness of readers is cached for at most `max_sleep_duration`, so that readers
+            // dying while writer waits for them are still noticed
+            if checked_at.elapsed() >= options.max_sleep_duration {
+                checked = LivenessCache::new();
+                checked_at = Instant::now();
+            }
+            let free_idx = (0..num_instances)
+                .map(|i| (next_idx + i) % num_instances)
+                .filter(|idx| Some(*idx) != current_idx)
+                .find(|&idx| {
+                    if self.readers_count(idx) != 0 {
+                        return false;
+                    }
+                    // release locks of registered readers which died without unlocking
+                    if self.registry.count(idx) != 0 {
+                        evicted += self.registry.evict_dead(idx, &mut checked);
+                    }
+                    self.registry.count(idx) == 0
+                });
+            if let Some(idx) = free_idx {
+                return Ok(Acquisition {
+                    idx,
+                    reset: reset && idx == next_idx,
+                    evicted,
+                });
+            }
+
+            // check number of readers using `next_idx`
+            let busy_shard = self.busy_reader_shard(next_idx);
+            let unregistered = self.readers_count(next_idx);
+            let registered = self.registry.count(next_idx);
+            if busy_shard.is_none() && registered == 0 {
+                // the last reader of `next_idx` has just left
+                continue;
+            }
+
+            let now = utils::unix_nanos(SystemTime::now());
+            let grace_remaining = grace_expiring_at.saturating_duration_since(Instant::now());
+            match policy {
+                ActiveReadersPolicy::Fail if grace_remaining.is_zero() => {
+                    return Err(ReadersStillActive {
+                        readers: unregistered + registered,
+                    });
+                }
+                ActiveReadersPolicy::Reset => {
+                    // registered readers are evicted once their leases expire, while live ones
+                    // without a lease are never evicted
+                    let mut released = self.registry.evict_expired(next_idx, now);
+
+                    // we should reset only when one of the unregistered readers dies without
+                    // decrement, or they outlive grace period
+                    if unregistered > 0 && grace_remaining.is_zero() {
+                        let reset_readers = self.reset_readers(next_idx);
+                        reset |= reset_readers > 0;
+                        released += reset_readers;
+                    }
+
+                    if released > 0 {
+                        evicted += released;
+                        continue;
+                    }
+                }
+                _ => {}
+            }
+
+            // sleep until grace period or the earliest lease expires unless woken up earlier by
+            // the last leaving reader
+            let mut sleep_duration = options.max_sleep_duration;
+            if unregistered > 0 && policy != ActiveReadersPolicy::Wait && !grace_remaining.is_zero()
+            {
+                sleep_duration = sleep_duration.min(grace_remaining);
+            }
+            if policy == ActiveReadersPolicy::Reset {
+                if let Some(lease_deadline) = self.registry.earliest_lease(next_idx) {
+                    let lease_remaining = Duration::from_nanos(lease_deadline.saturating_sub(now));
+                    sleep_duration = sleep_duration.min(lease_remaining);
+                }
+            }
+
+            // announce waiting before checking registered readers once again, so that either
+            // the last one leaving observes it and wakes writer up, or writer observes it leaving
+            self.writer_waiting.store(1, Ordering::SeqCst);
+            match busy_shard {
+                Some((num_readers, readers)) => {
+                    waiter.wait(
+                        futex::lower_half(num_readers),
+                        readers as u32,
+                        sleep_duration,
+                    );
+                }
+                None => {
+                    let unregistrations = self.unregistrations.load(Ordering::SeqCst);
+                    if self.registry.count(next_idx) > 0 {
+                        waiter.wait(&self.unregistrations, unregistrations, sleep_duration);
+                    }
+                }
+            }
+            self.writer_waiting.store(0, Ordering::SeqCst);
+        }
+    }
+
+    /// Resets readers counter of data instance `idx` across all the shards and starts new
+    /// generation, so that unlocks of readers counted before the reset are ignored. Returns number
+    /// of reset readers.
+    #[inline]
+    fn reset_readers(&self, idx: usize) -> u32 {
+        let mut reset = 0;
+        for shard in &self.idx_readers[idx] {
+            let res = shard
+                .0
+                .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |r| {
+                    Some((r & !READERS_COUNT_MASK).wrapping_add(1 << READERS_GENERATION_SHIFT))
+                });
+            reset += res.map_or(0, |r| (r & READERS_COUNT_MASK) as u32);
+        }
+        reset
+    }
+
+    /// Returns number of readers of data instance `idx` summed up across all the shards
+    #[inline]
+    fn readers_count(&self, idx: usize) -> u32 {
+        self.idx_readers[idx]
+            .iter()
+            .map(|shard| (shard.0.load(Ordering::SeqCst) & READERS_COUNT_MASK) as u32)
+            .fold(0, u32::wrapping_add)
+    }
+
+    /// Returns the first readers counter shard of data instance `idx` having any readers along
+    /// with its value, or `None` if there are no readers
+    #[inline]
+    fn busy_reader_shard(&self, idx: usize) -> Option<(&AtomicU64, u64)> {
+        self.idx_readers[idx]
+            .iter()
+            .map(|shard| (&shard.0, shard.0.load(Ordering::SeqCst)))
+            .find(|(_, readers)| readers & READERS_COUNT_MASK != 0)
+    }
+
+    /// Indicates whether given reader `lock` is still valid, i.e. it has been neither reset by
+    /// the writer after grace period nor evicted from the registry
+    #[inline]
+    pub(crate) fn is_lock_valid(&self, lock: &ReadLock) -> bool {
+        match lock.kind {
+            ReadLockKind::Counted { shard, generation } => {
+                let readers = self.idx_readers[lock.version.idx()][shard]
+                    .0
+                    .load(Ordering::SeqCst);
+                (readers >> READERS_GENERATION_SHIFT) as u32 == generation
+            }
+            ReadLockKind::Registered(registration) => self.registry.is_registered(registration),
+        }
+    }
+
+    /// Extends lease of given reader `lock` to expire after `lease` from now. Returns `false` if
+    /// the lock holds no lease or is no longer valid.
+    #[inline]
+    pub(crate) fn extend_lease(&self, lock: &ReadLock, lease: Duration) -> bool {
+        match lock.kind {
+            ReadLockKind::Counted { .. } => false,
+            ReadLockKind::Registered(registration) => self
+                .registry
+                .extend_lease(registration, lease_deadline(lease)),
+        }
+    }
+
+    /// Unlocks given reader `lock`.
+    ///
+    /// Unlock is ignored when the lock was released by the writer in the meantime, either because
+    /// readers counter was reset after the lock was taken or the reader was evicted from the
+    /// registry.
+    #[inline]
+    pub(crate) fn runlock(&self, lock: ReadLock) {
+        let released = match lock.kind {
+            ReadLockKind::Counted { shard, generation } => {
+                self.release_reader(lock.version.idx(), shard, generation)
+            }
+            ReadLockKind::Registered(registration) => {
+                let unregistered = self.registry.unregister(registration);
+                if unregistered && self.writer_waiting.load(Ordering::SeqCst) != 0 {
+                    self.unregistrations.fetch_add(1, Ordering::SeqCst);
+                    futex::wake_all(&self.unregistrations);
+                }
+                unregistered
+            }
+        };
+
+        if !released {
+            self.stale_unlocks.fetch_add(1, Ordering::SeqCst);
+        }
+    }
+
+    /// Decrements number of readers of data instance `idx` counted in `shard` locked with reset
+    /// `generation`, waking up waiting writer once the last reader of the shard leaves.
+    /// Returns `false` if readers counter has been reset since then.
+    #[inline]
+    fn release_reader(&self, idx: usize, shard: usize, generation: u32) -> bool {
+        let num_readers = &self.idx_readers[idx][shard].0;
+        let res = num_readers.fetch_update(Ordering::SeqCst, Ordering::SeqCst, |readers| {
+            let stale = (readers >> READERS_GENERATION_SHIFT) as u32 != generation;
+            match stale || readers & READERS_COUNT_MASK == 0 {
+                true => None,
+                false => Some(readers - 1),
+            }
+        });
+
+        match res {
+            Ok(readers) => {
+                if readers & READERS_COUNT_MASK == 1
+                    && self.writer_waiting.load(Ordering::SeqCst) != 0
+                {
+                    futex::wake_all(futex::lower_half(num_readers));
+                }
+                true
             }
-            thread::sleep(sleep_duration);
+            Err(_) => false,
         }
+    }
 
-        (next_idx, reset)
+    /// Returns number of stale reader unlocks ignored since the last call
+    #[inline]
+    pub(crate) fn take_stale_unlocks(&self) -> u32 {
+        self.stale_unlocks.swap(0, Ordering::SeqCst)
     }
 
-    /// Unlocks given `version` from reading
+    /// Return header of the data instance stored in data file `idx`
     #[inline]
-    pub(crate) fn runlock(&mut self, version: InstanceVersion) {
-        self.idx_readers[version.idx()].fetch_sub(1, Ordering::SeqCst);
+    pub(crate) fn data_header(&self, idx: usize) -> &DataHeader {
+        &self.data_headers[idx]
     }
 
-    /// Switch state to given `version`
+    /// Switch state from `current` version, loaded before acquiring data instance of `version`,
+    /// to given `version` of data with full-width `checksum`, archived type `type_fingerprint`
+    /// and publication `metadata`, returns publication generation of the version.
+    ///
+    /// Next publication generation is claimed first by a single CAS, which fails once writer's
+    /// `fencing_epoch` gets superseded, because acquisitions of fenced write lock advance the
+    /// generation right after the epoch. Fenced writers then publish the version by a CAS
+    /// against `current` version, so that a writer superseded in the meantime never replaces
+    /// a version published by its successor, but fails with `FencedOut` error instead.
     #[inline]
-    pub(crate) fn switch_version(&mut self, version: InstanceVersion) {
+    pub(crate) fn switch_version(
+        &self,
+        current: Option<InstanceVersion>,
+        version: InstanceVersion,
+        checksum: u64,
+        type_fingerprint: u64,
+        metadata: &Metadata,
+        fencing_epoch: Option<u64>,
+    ) -> Result<u64, SynchronizerError> {
+        let mut generation = self.generation.load(Ordering::SeqCst);
+        loop {
+            self.check_fencing_epoch(fencing_epoch)?;
+            match self.generation.compare_exchange(
+                generation,
+                generation + 1,
+                Ordering::SeqCst,
+                Ordering::SeqCst,
+            ) {
+                Ok(_) => break,
+                Err(current) => generation = current,
+            }
+        }
+        let generation = generation + 1;
+
+        // record header of the new version before publishing it, so that readers of the version
+        // always observe its header
+        let header = &self.data_headers[version.idx()];
+        header.generation.store(generation, Ordering::SeqCst);
+        header.checksum.store(checksum, Ordering::SeqCst);
+        header
+            .type_fingerprint
+            .store(type_fingerprint, Ordering::SeqCst);
+        header
+            .published_at
+            .store(metadata.published_at_nanos(), Ordering::SeqCst);
+        header
+            .hostname_hash
+            .store(metadata.hostname_hash(), Ordering::SeqCst);
+        header
+            .writer_pid
+            .store(metadata.writer_pid(), Ordering::SeqCst);
+
         // actually change current data file index in memory mapped state
         // so new readers can switch to it when calling `read`
-        self.version.swap(version.into(), Ordering::SeqCst);
+        match fencing_epoch {
+            None => {
+                self.version.swap(version.into(), Ordering::SeqCst);
+            }
+            Some(_) => {
+                #[cfg(test)]
+                if let Some(hook) = SWITCH_HOOK.with(|hook| hook.borrow_mut().take()) {
+                    hook();
+                }
+                let mut current = current.map_or(0, u64::from);
+                while let Err(actual) = self.version.compare_exchange(
+                    current,
+                    version.into(),
+                    Ordering::SeqCst,
+                    Ordering::SeqCst,
+                ) {
+                    // version published while this writer still held the current epoch can't
+                    // be a version of its successor, so it is replaced
+                    self.check_fencing_epoch(fencing_epoch)?;
+                    current = actual;
+                }
+            }
+        }
+        self.heartbeat
+            .store(metadata.published_at_nanos(), Ordering::SeqCst);
+
+        // wake up readers waiting for version change, only once the version is visible to them
+        self.switches.fetch_add(1, Ordering::SeqCst);
+        futex::wake_all(&self.switches);
+
+        Ok(generation)
+    }
+
+    /// Return publication metadata of state's current version.
+    ///
+    /// Metadata is loaded again if the writer switched versions while it was being loaded.
+    pub(crate) fn metadata(&self) -> Result<Metadata, SynchronizerError> {
+        loop {
+            let generation = self.generation();
+            let metadata = self.data_headers[self.version()?.idx()].metadata();
+            if self.generation() == generation {
+                return Ok(metadata);
+            }
+        }
+    }
+
+    /// Wait until state's version differs from `last` version or `timeout` elapses.
+    /// Returns changed version, or `None` if it did not change before `timeout`.
+    ///
+    /// Waiting is done on the number of version switches, which changes only once the new
+    /// version is visible.
+    pub(crate) fn wait_for_change(
+        &self,
+        last: InstanceVersion,
+        timeout: Duration,
+    ) -> Result<Option<InstanceVersion>, SynchronizerError> {
+        let deadline = Instant::now().add(timeout);
+        let mut waiter = Waiter::new();
+        loop {
+            let switches = self.switches.load(Ordering::SeqCst);
+            let version = self.version.load(Ordering::SeqCst);
+            if version != u64::from(last) {
+                return version.try_into().map(Some);
+            }
+
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            if remaining.is_zero() {
+                return Ok(None);
+            }
+            waiter.wait(&self.switches, switches, remaining);
+        }
+    }
+
+    /// Block for as long as state's number of version switches equals `switches`, but no longer
+    /// than `timeout`. Returns current number of version switches.
+    #[cfg(feature = "tokio")]
+    pub(crate) fn wait_for_switch(&self, switches: u32, timeout: Duration) -> u32 {
+        let deadline = Instant::now().add(timeout);
+        let mut waiter = Waiter::new();
+        loop {
+            let current = self.switches();
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            if current != switches || remaining.is_zero() {
+                return current;
+            }
+            waiter.wait(&self.switches, switches, remaining);
+        }
     }
 }
 
@@ -101,28 +903,111 @@ impl Default for State {
     }
 }
 
+impl StateLayout for State {
+    const SUFFIX: &'static str = "_state";
+    const MAGIC: [u8; 8] = STATE_MAGIC;
+    const LAYOUT_VERSION: u32 = STATE_LAYOUT_VERSION;
+
+    #[inline]
+    fn header(&self) -> &StateHeader {
+        &self.header
+    }
+
+    #[inline]
+    fn fencing(&self) -> &AtomicU64 {
+        &self.fencing_epoch
+    }
+
+    /// Advance fencing epoch along with publication generation, so that writers fenced out by
+    /// the acquisition fail to claim their next generation in `switch_version`
+    #[inline]
+    fn advance_fencing_epoch(&self) -> u64 {
+        let epoch = self.fencing_epoch.fetch_add(1, Ordering::SeqCst) + 1;
+        self.generation.fetch_add(1, Ordering::SeqCst);
+        epoch
+    }
+}
+
 /// State container stores memory mapped state file, which is used for
 /// synchronization purposes with a help of atomics
-pub(crate) struct StateContainer<WL> {
+pub(crate) struct StateContainer<WL, S = State> {
     /// State file path
     state_path: OsString,
     /// Modifiable memory mapped file storing state.
     ///
-    /// The [`MmapMut`] type is wrapped in a [`WriteLockStrategy`] to require lock acquisition
+    /// The [`StateMmap`] type is wrapped in a [`WriteLockStrategy`] to require lock acquisition
     /// prior to writing.
     mmap: Option<WL>,
+    /// Mode of write lock acquisition, defaults to the one of the lock strategy
+    lock_mode: Option<LockMode>,
+    /// Fencing epoch of the write lock held by this container, if the lock is fenced
+    fencing_epoch: Option<u64>,
+    /// Flag raised once write lock held by this container is released, which stops heartbeats
+    /// recorded on behalf of this container
+    released: Arc<AtomicBool>,
+    /// Number of 64-bit words of data stored inline right after the state
+    inline_words: usize,
+    /// State mapped for reading independently of this container, shared by all the change
+    /// notifications and owned read results of this container
+    shared: Option<SharedState>,
+    _layout: PhantomData<S>,
 }
 
-const STATE_SUFFIX: &str = "_state";
-
-impl<'a, WL: WriteLockStrategy<'a>> StateContainer<WL> {
+impl<'a, WL: WriteLockStrategy<'a>, S: StateLayout> StateContainer<WL, S> {
     /// Create new instance of `StateContainer`
     pub(crate) fn new(path_prefix: &OsStr) -> Self {
         let mut state_path = path_prefix.to_os_string();
-        state_path.push(STATE_SUFFIX);
+        state_path.push(S::SUFFIX);
         StateContainer {
             state_path,
             mmap: None,
+            lock_mode: None,
+            fencing_epoch: None,
+            released: Arc::default(),
+            inline_words: 0,
+            shared: None,
+            _layout: PhantomData,
+        }
+    }
+
+    /// Reserve given number of 64-bit words for data stored inline right after the state
+    pub(crate) fn with_inline_words(mut self, inline_words: usize) -> Self {
+        self.inline_words = inline_words;
+        self
+    }
+
+    /// Create new instance of `StateContainer` for the same state file without write locking,
+    /// allowing to access the state independently of this container
+    pub(crate) fn detached(&self) -> StateContainer<LockDisabled, S> {
+        StateContainer {
+            state_path: self.state_path.clone(),
+            mmap: None,
+            lock_mode: None,
+            fencing_epoch: None,
+            released: Arc::default(),
+            inline_words: self.inline_words,
+            shared: None,
+            _layout: PhantomData,
+        }
+    }
+
+    /// Set mode of write lock acquisition
+    pub(crate) fn set_lock_mode(&mut self, lock_mode: LockMode) {
+        self.lock_mode = Some(lock_mode);
+    }
+
+    /// Return flag raised once write lock currently held by this container is released
+    pub(crate) fn released(&self) -> Arc<AtomicBool> {
+        self.released.clone()
+    }
+
+    /// Release write lock if it is held, so that another writer can acquire it
+    pub(crate) fn release_write_lock(&mut self) -> Result<(), SynchronizerError> {
+        // stop heartbeats of this lock holder before another writer can take over
+        mem::take(&mut self.released).store(true, Ordering::SeqCst);
+        match &mut self.mmap {
+            Some(mmap) => mmap.release(),
+            None => Ok(()),
         }
     }
 
@@ -134,21 +1019,87 @@ impl<'a, WL: WriteLockStrategy<'a>> StateContainer<WL> {
     pub(crate) fn state<const WRITE: bool>(
         &'a mut self,
         create: bool,
-    ) -> Result<&'a mut State, SynchronizerError> {
+    ) -> Result<&'a mut S, SynchronizerError> {
         if self.mmap.is_none() {
             self.prepare_mmap(create)?;
         }
 
         if WRITE {
-            let mut guard = self.mmap.as_mut().unwrap().lock()?;
-            Ok(unsafe { &mut *(guard.deref_mut().as_ptr() as *mut State) })
+            self.write_state(create).map(|(state, _)| state)
         } else {
             let mmap = self.mmap.as_ref().unwrap().read();
-            Ok(unsafe { &mut *(mmap.as_ptr() as *mut State) })
+            Ok(unsafe { &mut *(mmap.as_ptr() as *mut S) })
+        }
+    }
+
+    /// Fetch state for writing from existing memory mapped file or create new one, along with
+    /// fencing epoch of the write lock if the lock is fenced.
+    ///
+    /// Fencing epoch is advanced on the first acquisition of fenced write lock, and an error is
+    /// returned if it has been advanced by another writer since then. Writer keeps its epoch once
+    /// it releases the lock, so that it fails right away instead of waiting for the lock held by
+    /// the writer which took over.
+    #[inline]
+    pub(crate) fn write_state(
+        &'a mut self,
+        create: bool,
+    ) -> Result<(&'a mut S, Option<u64>), SynchronizerError> {
+        if self.mmap.is_none() {
+            self.prepare_mmap(create)?;
+        }
+
+        let acquiring = !self.mmap.as_ref().unwrap().is_locked();
+        if WL::FENCING && acquiring && self.fencing_epoch.is_some() {
+            self.detached()
+                .state::<false>(false)?
+                .check_fencing_epoch(self.fencing_epoch)?;
+        }
+
+        let mmap = self.mmap.as_mut().unwrap();
+        let mut guard = mmap.lock(self.lock_mode.unwrap_or(WL::DEFAULT_LOCK_MODE))?;
+        let state = unsafe { &mut *(guard.deref_mut().as_mut_ptr() as *mut S) };
+
+        if WL::FENCING {
+            if self.fencing_epoch.is_none() {
+                self.fencing_epoch = Some(state.advance_fencing_epoch());
+            }
+            state.check_fencing_epoch(self.fencing_epoch)?;
         }
+
+        Ok((state, self.fencing_epoch))
+    }
+
+    /// Return state from memory mapped file if it has already been mapped, allowing to access it
+    /// through a shared reference
+    #[inline]
+    pub(crate) fn mapped_state(&'a self) -> Option<&'a S> {
+        let mmap = self.mmap.as_ref()?.read();
+        Some(unsafe { &*(mmap.as_ptr() as *const S) })
+    }
+
+    /// Fetch state along with data stored inline right after it the same way as `state` does,
+    /// which is zeroed until written for the first time.
+    #[inline]
+    pub(crate) fn inline_data<const WRITE: bool>(
+        &'a mut self,
+        create: bool,
+    ) -> Result<(&'a S, &'a [AtomicU64]), SynchronizerError> {
+        let inline_words = self.inline_words;
+        let state = self.state::<WRITE>(create)?;
+
+        // inline data follows the state, whose size is a multiple of 8 bytes, and the mapping has
+        // been checked to cover it in `prepare_mmap`
+        let ptr = unsafe { (state as *const S).add(1) as *const AtomicU64 };
+        Ok((state, unsafe {
+            std::slice::from_raw_parts(ptr, inline_words)
+        }))
     }
 
     /// Initialize mmaped memory from the state file.
+    ///
+    /// State file is only ever resized and initialized when `create` is set, i.e. by writers.
+    /// Existing state files with incompatible layout, or too short to store inline data, are
+    /// rejected with an error.
     #[inline]
     pub(crate) fn prepare_mmap(&mut self, create: bool) -> Result<(), SynchronizerError> {
         let mut opts = OpenOptions::new();
@@ -160,37 +1111,176 @@ impl<'a, WL: WriteLockStrategy<'a>> StateContainer<WL> {
 
         let state_file = opts.open(&self.state_path).map_err(FailedStateRead)?;
 
-        let mut need_init = false;
-        // Reset state file size to match exactly `STATE_SIZE`
-        if state_file.metadata().map_err(FailedStateRead)?.len() != STATE_SIZE as u64 {
-            utils::set_len(&state_file, STATE_SIZE as i64).map_err(FailedStateRead)?;
-            need_init = true;
+        let state_len = mem::size_of::<S>() + self.inline_words * mem::size_of::<AtomicU64>();
+        let len = state_file.metadata().map_err(FailedStateRead)?.len();
+        if len < mem::size_of::<StateHeader>() as u64 {
+            if !create {
+                return Err(UninitializedState);
+            }
+            // Extend empty state file to match exactly the size of the state along with inline data
+            utils::set_len(&state_file, state_len as i64).map_err(FailedStateRead)?;
         }
 
         let mut mmap = unsafe { MmapMut::map_mut(&state_file).map_err(FailedStateRead)? };
-        if need_init {
-            // Create new state and write it to mapped memory
-            let new_state = State::default();
-            unsafe {
-                mmap.as_mut_ptr()
-                    .copy_from((&new_state as *const State) as *const u8, STATE_SIZE);
+        let header = unsafe { &*(mmap.as_ptr() as *const StateHeader) };
+        match header.validate::<S>() {
+            Ok(()) if mmap.len() < state_len => return Err(InvalidStateFile),
+            Ok(()) => {}
+            Err(UninitializedState) if create => {
+                if mmap.len() < state_len {
+                    return Err(InvalidStateFile);
+                }
+                Self::initialize(&mut mmap)?;
             }
+            Err(err) => return Err(err),
         }
 
-        self.mmap = Some(WL::new(mmap, state_file));
+        self.mmap = Some(WL::new(StateMmap::new(mmap), state_file));
         Ok(())
     }
+
+    /// Initialize new state within uninitialized `mmap`, unless another writer creating the same
+    /// state file claimed its initialization first, in which case wait for it to finish.
+    ///
+    /// Initialization is claimed by a CAS on the magic bytes, so that a state already in use is
+    /// never overwritten by a writer which observed it uninitialized just before.
+    fn initialize(mmap: &mut MmapMut) -> Result<(), SynchronizerError> {
+        let ptr = mmap.as_mut_ptr();
+        let header = unsafe { &*(ptr as *const S) }.header();
+        debug_assert_eq!(
+            &header.magic as *const AtomicU64 as *const u8,
+            ptr as *const u8
+        );
+        let claimed = header.magic.compare_exchange(
+            0,
+            u64::from_ne_bytes(INITIALIZING_MAGIC),
+            Ordering::SeqCst,
+            Ordering::SeqCst,
+        );
+        if claimed.is_err() {
+            let deadline = Instant::now().add(INITIALIZATION_TIMEOUT);
+            loop {
+                match header.validate::<S>() {
+                    Err(UninitializedState) if Instant::now() < deadline => {
+                        std::thread::sleep(Duration::from_millis(1))
+                    }
+                    res => return res,
+                }
+            }
+        }
+
+        // Create new state and write it to mapped memory, except for the claimed magic bytes
+        let new_state = S::default();
+        let magic_len = mem::size_of::<AtomicU64>();
+        unsafe {
+            ptr.add(magic_len).copy_from(
+                (&new_state as *const S as *const u8).add(magic_len),
+                mem::size_of::<S>() - magic_len,
+            );
+        }
+
+        // Mark state as initialized only once it has been fully written
+        header
+            .magic
+            .store(u64::from_ne_bytes(S::MAGIC), Ordering::SeqCst);
+        Ok(())
+    }
+}
+
+impl<'a, WL: WriteLockStrategy<'a>> StateContainer<WL> {
+    /// Return the same state as this container does mapped for reading independently of it,
+    /// mapping it on first call only
+    pub(crate) fn shared_state(&mut self) -> Result<SharedState, SynchronizerError> {
+        if let Some(shared) = &self.shared {
+            return Ok(shared.clone());
+        }
+
+        let mut state_container = self.detached();
+        state_container.prepare_mmap(false)?;
+        let shared = SharedState::new(state_container);
+        self.shared = Some(shared.clone());
+        Ok(shared)
+    }
+}
+
+impl<WL, S> Drop for StateContainer<WL, S> {
+    /// Stops heartbeats of the writer before its write lock, if any, is released along with
+    /// the mapping
+    fn drop(&mut self) {
+        self.released.store(true, Ordering::SeqCst);
+    }
+}
+
+/// Reference counted handle of the state mapped for reading, keeping the mapping alive for as
+/// long as any handle exists
+#[derive(Clone)]
+pub(crate) struct SharedState(Arc<SharedMapping>);
+
+/// State mapping shared by `SharedState` handles
+struct SharedMapping {
+    state_container: StateContainer<LockDisabled>,
+    /// Watcher of publications notifying change streams of this mapping
+    #[cfg(feature = "tokio")]
+    watcher: crate::stream::PublicationWatcher,
+}
+
+impl SharedState {
+    /// Wrap given already mapped `state_container` into a shared handle
+    fn new(state_container: StateContainer<LockDisabled>) -> Self {
+        SharedState(Arc::new(SharedMapping {
+            state_container,
+            #[cfg(feature = "tokio")]
+            watcher: Default::default(),
+        }))
+    }
+
+    /// Map existing state file derived from `path_prefix` for reading
+    pub(crate) fn map(path_prefix: &OsStr) -> Result<Self, SynchronizerError> {
+        let mut state_container = StateContainer::new(path_prefix);
+        state_container.prepare_mmap(false)?;
+        Ok(Self::new(state_container))
+    }
+
+    /// Return publication watcher of this mapping
+    #[cfg(feature = "tokio")]
+    pub(crate) fn watcher(&self) -> &crate::stream::PublicationWatcher {
+        &self.0.watcher
+    }
+
+    /// Subscribe to version switches of this mapping
+    #[cfg(feature = "tokio")]
+    pub(crate) fn subscribe(&self) -> tokio::sync::watch::Receiver<u32> {
+        self.0.watcher.subscribe(self)
+    }
+}
+
+impl Deref for SharedState {
+    type Target = State;
+
+    fn deref(&self) -> &State {
+        self.0
+            .state_container
+            .mapped_state()
+            .expect("shared state is mapped on creation")
+    }
 }
 
 #[cfg(all(test, unix))]
 mod tests {
     use super::*;
-    use crate::locks::SingleWriter;
+    #[cfg(target_os = "linux")]
+    use crate::locks::OfdWriter;
+    use crate::locks::{Failover, LockDisabled, SingleWriter};
     use crate::synchronizer::SynchronizerError;
+    use std::fs;
+    use std::thread;
+
+    const STATE_SIZE: usize = mem::size_of::<State>();
 
     #[test]
     fn single_writer_lock_mode_prevents_duplicate_writer() {
         static PATH: &str = "/tmp/single_writer_lock_test";
+        fs::remove_file(PATH.to_owned() + "_state").unwrap_or_default();
         let mut state1 = StateContainer::<SingleWriter>::new(PATH.as_ref());
         let mut state2 = StateContainer::<SingleWriter>::new(PATH.as_ref());
 
@@ -204,6 +1294,7 @@ mod tests {
     #[test]
     fn single_writer_lock_freed_on_drop() {
         static PATH: &str = "/tmp/single_writer_lock_drop_test";
+        fs::remove_file(PATH.to_owned() + "_state").unwrap_or_default();
         let mut state1 = StateContainer::<SingleWriter>::new(PATH.as_ref());
         let mut state2 = StateContainer::<SingleWriter>::new(PATH.as_ref());
 
@@ -211,4 +1302,532 @@ mod tests {
         drop(state1);
         assert!(state2.state::<true>(true).is_ok());
     }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn ofd_writer_lock_excludes_writers_within_process() {
+        static PATH: &str = "/tmp/ofd_writer_lock_test";
+        fs::remove_file(PATH.to_owned() + "_state").unwrap_or_default();
+        let mut state1 = StateContainer::<OfdWriter>::new(PATH.as_ref());
+        assert!(state1.state::<true>(true).is_ok());
+
+        thread::scope(|s| {
+            s.spawn(|| {
+                let mut state2 = StateContainer::<OfdWriter>::new(PATH.as_ref());
+                assert!(matches!(
+                    state2.state::<true>(true),
+                    Err(SynchronizerError::WriteLockConflict)
+                ));
+            })
+            .join()
+            .unwrap();
+
+            state1.release_write_lock().unwrap();
+            s.spawn(|| {
+                let mut state2 = StateContainer::<OfdWriter>::new(PATH.as_ref());
+                assert!(state2.state::<true>(true).is_ok());
+            });
+        });
+    }
+
+    #[test]
+    fn reader_never_initializes_state_file() {
+        static PATH: &str = "/tmp/state_header_empty_test";
+        let state_path = PATH.to_owned() + "_state";
+        fs::write(&state_path, []).unwrap();
+
+        let mut reader = StateContainer::<LockDisabled>::new(PATH.as_ref());
+        assert!(matches!(
+            reader.state::<false>(false),
+            Err(SynchronizerError::UninitializedState)
+        ));
+        assert_eq!(fs::metadata(&state_path).unwrap().len(), 0);
+
+        let mut writer = StateContainer::<LockDisabled>::new(PATH.as_ref());
+        assert!(writer.state::<true>(true).is_ok());
+        assert_eq!(fs::metadata(&state_path).unwrap().len(), STATE_SIZE as u64);
+        assert!(reader.state::<false>(false).is_ok());
+    }
+
+    #[test]
+    fn incompatible_layout_version_is_rejected() {
+        static PATH: &str = "/tmp/state_header_layout_test";
+        let state_path = PATH.to_owned() + "_state";
+        fs::remove_file(&state_path).unwrap_or_default();
+
+        let mut writer = StateContainer::<LockDisabled>::new(PATH.as_ref());
+        writer.state::<true>(true).unwrap().header.layout_version = STATE_LAYOUT_VERSION + 1;
+        drop(writer);
+
+        for create in [false, true] {
+            let mut state = StateContainer::<LockDisabled>::new(PATH.as_ref());
+            assert!(matches!(
+                state.state::<false>(create),
+                Err(SynchronizerError::IncompatibleStateLayout { expected, found })
+                    if expected == STATE_LAYOUT_VERSION && found == STATE_LAYOUT_VERSION + 1
+            ));
+        }
+    }
+
+    #[test]
+    fn state_file_of_other_layout_version_is_rejected() {
+        static PATH: &str = "/tmp/state_header_other_layout_test";
+        let state_path = PATH.to_owned() + "_state";
+
+        // state file written by another release, sharing nothing but the header with this one
+        let mut file = vec![0; STATE_SIZE];
+        file[..8].copy_from_slice(&STATE_MAGIC);
+        file[8..12].copy_from_slice(&2u32.to_ne_bytes());
+        file[12] = STATE_ENDIANNESS;
+        file[13] = STATE_POINTER_WIDTH;
+        fs::write(&state_path, &file).unwrap();
+
+        let mut writer = StateContainer::<LockDisabled>::new(PATH.as_ref());
+        assert!(matches!(
+            writer.state::<true>(true),
+            Err(SynchronizerError::IncompatibleStateLayout {
+                expected: 1,
+                found: 2
+            })
+        ));
+        assert_eq!(fs::read(&state_path).unwrap(), file);
+    }
+
+    #[test]
+    fn garbage_state_file_is_rejected() {
+        static PATH: &str = "/tmp/state_header_garbage_test";
+        let state_path = PATH.to_owned() + "_state";
+        fs::write(&state_path, vec![0xAB; STATE_SIZE]).unwrap();
+
+        let mut writer = StateContainer::<LockDisabled>::new(PATH.as_ref());
+        assert!(matches!(
+            writer.state::<true>(true),
+            Err(SynchronizerError::InvalidStateFile)
+        ));
+        assert_eq!(fs::read(&state_path).unwrap(), vec![0xAB; STATE_SIZE]);
+    }
+
+    #[test]
+    fn concurrently_created_state_is_initialized_once() {
+        static PATH: &str = "/tmp/state_concurrent_init_test";
+        const WRITERS: u64 = 8;
+
+        for _ in 0..20 {
+            fs::remove_file(PATH.to_owned() + "_state").unwrap_or_default();
+            thread::scope(|s| {
+                for _ in 0..WRITERS {
+                    s.spawn(|| {
+                        let mut writer = StateContainer::<LockDisabled>::new(PATH.as_ref());
+                        writer.state::<true>(true).unwrap().advance_fencing_epoch();
+                    });
+                }
+            });
+
+            // none of the writers initialized state already used by another one
+            let mut reader = StateContainer::<LockDisabled>::new(PATH.as_ref());
+            assert_eq!(
+                reader.state::<false>(false).unwrap().fencing_epoch(),
+                WRITERS
+            );
+        }
+    }
+
+    #[test]
+    fn fenced_out_writer_cannot_publish() {
+        static PATH: &str = "/tmp/state_fencing_test";
+        fs::remove_file(PATH.to_owned() + "_state").unwrap_or_default();
+        let version = InstanceVersion::new(0, 1, 0).unwrap();
+        let mut primary = StateContainer::<Failover>::new(PATH.as_ref());
+        let mut standby = StateContainer::<Failover>::new(PATH.as_ref());
+
+        let (_, primary_epoch) = primary.write_state(true).unwrap();
+        assert_eq!(primary_epoch, Some(1));
+
+        // primary loses its lock while it's paused, so that standby takes over
+        primary.release_write_lock().unwrap();
+        let (state, standby_epoch) = standby.write_state(true).unwrap();
+        assert_eq!(standby_epoch, Some(2));
+        assert!(matches!(
+            state.switch_version(None, version, 0, 0, &Metadata::current(), primary_epoch),
+            Err(SynchronizerError::FencedOut {
+                epoch: 1,
+                current: 2
+            })
+        ));
+        assert!(state
+            .switch_version(None, version, 0, 0, &Metadata::current(), standby_epoch)
+            .is_ok());
+
+        // primary keeps its superseded epoch instead of taking over again
+        assert!(matches!(
+            primary.write_state(true),
+            Err(SynchronizerError::FencedOut {
+                epoch: 1,
+                current: 2
+            })
+        ));
+    }
+
+    #[test]
+    fn writer_fenced_out_before_publishing_never_replaces_version_of_successor() {
+        static PATH: &str = "/tmp/state_fencing_switch_test";
+        fs::remove_file(PATH.to_owned() + "_state").unwrap_or_default();
+        let first = InstanceVersion::new(0, 1, 0).unwrap();
+        let stale = InstanceVersion::new(1, 2, 0).unwrap();
+        let successor = InstanceVersion::new(0, 3, 0).unwrap();
+        let mut primary = StateContainer::<Failover>::new(PATH.as_ref());
+
+        let (state, epoch) = primary.write_state(true).unwrap();
+        state
+            .switch_version(None, first, 0, 0, &Metadata::current(), epoch)
+            .unwrap();
+
+        // standby, which failed to be excluded by the lock, takes over and publishes after
+        // primary passed its epoch checks, but before it switched to its version
+        SWITCH_HOOK.with(|hook| {
+            *hook.borrow_mut() = Some(Box::new(move || {
+                let mut standby = StateContainer::<LockDisabled>::new(PATH.as_ref());
+                let state = standby.state::<true>(false).unwrap();
+                let epoch = state.advance_fencing_epoch();
+                let current = state.version().ok();
+                state
+                    .switch_version(current, successor, 0, 0, &Metadata::current(), Some(epoch))
+                    .unwrap();
+            }))
+        });
+        assert!(matches!(
+            state.switch_version(Some(first), stale, 0, 0, &Metadata::current(), epoch),
+            Err(SynchronizerError::FencedOut {
+                epoch: 1,
+                current: 2
+            })
+        ));
+        assert_eq!(state.version().unwrap(), successor);
+    }
+
+    /// Acquires next data instance out of two, resetting readers after grace period
+    fn acquire(
+        state: &State,
+        grace_duration: Duration,
+        max_sleep_duration: Duration,
+    ) -> (usize, bool) {
+        let acquisition = state
+            .acquire_next_idx(&AcquireOptions {
+                num_instances: 2,
+                grace_duration,
+                max_sleep_duration,
+                policy: ActiveReadersPolicy::Reset,
+                strict: false,
+            })
+            .unwrap();
+        (acquisition.idx, acquisition.reset)
+    }
+
+    /// Switches `state` to given `version` the way writer does, without fencing
+    fn publish(state: &State, version: InstanceVersion) {
+        state
+            .switch_version(None, version, 0, 0, &Metadata::current(), None)
+            .unwrap();
+    }
+
+    /// Locks given `version` for reading without registering the reader
+    fn rlock_unregistered(state: &State, version: InstanceVersion) -> ReadLock {
+        state.rlock(version, &ReadOptions::default())
+    }
+
+    /// Returns readers counter shard and reset generation of counted reader `lock`
+    fn counted(lock: &ReadLock) -> (usize, u32) {
+        match lock.kind {
+            ReadLockKind::Counted { shard, generation } => (shard, generation),
+            ReadLockKind::Registered(_) => panic!("reader lock is registered"),
+        }
+    }
+
+    #[test]
+    fn stale_unlock_after_reset_is_ignored() {
+        let state = State::new();
+        publish(&state, InstanceVersion::new(0, 1, 0).unwrap());
+        let next_version = InstanceVersion::new(1, 1, 0).unwrap();
+
+        // lock next data instance and let writer reset it after grace period
+        let lock = rlock_unregistered(&state, next_version);
+        assert!(state.is_lock_valid(&lock));
+        let options = AcquireOptions {
+            num_instances: 2,
+            grace_duration: Duration::ZERO,
+            max_sleep_duration: Duration::ZERO,
+            policy: ActiveReadersPolicy::Reset,
+            strict: false,
+        };
+        assert_eq!(
+            state.acquire_next_idx(&options).unwrap(),
+            Acquisition {
+                idx: 1,
+                reset: true,
+                evicted: 1
+            }
+        );
+
+        // late unlock of the reader registered before reset must not affect newer readers
+        assert!(!state.is_lock_valid(&lock));
+        let new_lock = rlock_unregistered(&state, next_version);
+        assert!(state.is_lock_valid(&new_lock));
+        assert_ne!(counted(&lock).1, counted(&new_lock).1);
+        state.runlock(lock);
+        assert_eq!(state.take_stale_unlocks(), 1);
+        assert_eq!(state.readers_count(1), 1);
+
+        state.runlock(new_lock);
+        assert_eq!(state.take_stale_unlocks(), 0);
+        assert_eq!(acquire(&state, Duration::ZERO, Duration::ZERO), (1, false));
+    }
+
+    #[test]
+    fn sharded_readers_are_summed_up_by_writer() {
+        let state = State::new();
+        publish(&state, InstanceVersion::new(0, 1, 0).unwrap());
+        let next_version = InstanceVersion::new(1, 1, 0).unwrap();
+
+        // lock next data instance from several threads counted in different shards
+        let options = ReadOptions {
+            sharded: true,
+            ..ReadOptions::default()
+        };
+        let locks: Vec<_> = thread::scope(|s| {
+            let readers: Vec<_> = (0..4)
+                .map(|_| s.spawn(|| state.rlock(next_version, &options)))
+                .collect();
+            readers.into_iter().map(|r| r.join().unwrap()).collect()
+        });
+        assert!(locks
+            .iter()
+            .any(|lock| counted(lock).0 != counted(&locks[0]).0));
+        assert_eq!(state.readers_count(1), 4);
+
+        // writer resets all the shards together
+        let options = AcquireOptions {
+            num_instances: 2,
+            grace_duration: Duration::ZERO,
+            max_sleep_duration: Duration::ZERO,
+            policy: ActiveReadersPolicy::Reset,
+            strict: false,
+        };
+        assert_eq!(
+            state.acquire_next_idx(&options).unwrap(),
+            Acquisition {
+                idx: 1,
+                reset: true,
+                evicted: 4
+            }
+        );
+        assert!(locks.iter().all(|lock| !state.is_lock_valid(lock)));
+        assert_eq!(state.readers_count(1), 0);
+    }
+
+    #[test]
+    fn strict_mode_never_resets_readers() {
+        let state = State::new();
+        publish(&state, InstanceVersion::new(0, 1, 0).unwrap());
+        let lock = rlock_unregistered(&state, InstanceVersion::new(1, 1, 0).unwrap());
+
+        let mut options = AcquireOptions {
+            num_instances: 2,
+            grace_duration: Duration::ZERO,
+            max_sleep_duration: Duration::ZERO,
+            policy: ActiveReadersPolicy::Reset,
+            strict: true,
+        };
+        assert!(matches!(
+            state.acquire_next_idx(&options),
+            Err(SynchronizerError::ReadersStillActive { readers: 1 })
+        ));
+
+        // strict mode stays enabled for writers not asking for it
+        options.strict = false;
+        assert!(state.is_strict());
+        assert!(state.acquire_next_idx(&options).is_err());
+
+        state.runlock(lock);
+        assert_eq!(state.acquire_next_idx(&options).unwrap().idx, 1);
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn live_registered_reader_is_never_reset() {
+        let state = State::new();
+        publish(&state, InstanceVersion::new(0, 1, 0).unwrap());
+        let options = ReadOptions {
+            registered: true,
+            ..ReadOptions::default()
+        };
+        let lock = state.rlock(InstanceVersion::new(1, 1, 0).unwrap(), &options);
+        assert!(matches!(lock.kind, ReadLockKind::Registered(_)));
+
+        thread::scope(|s| {
+            let writer = s.spawn(|| acquire(&state, Duration::ZERO, Duration::from_millis(1)));
+
+            // writer keeps waiting for the live reader well past the grace period
+            thread::sleep(Duration::from_millis(50));
+            assert!(!writer.is_finished());
+
+            state.runlock(lock);
+            assert_eq!(writer.join().unwrap(), (1, false));
+        });
+        assert_eq!(state.take_stale_unlocks(), 0);
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn strict_mode_evicts_only_dead_registered_readers() {
+        use crate::registry::tests::{dead_pid, set_pid};
+
+        let state = State::new();
+        publish(&state, InstanceVersion::new(0, 1, 0).unwrap());
+        let version = InstanceVersion::new(1, 1, 0).unwrap();
+        let options = ReadOptions {
+            registered: true,
+            ..ReadOptions::default()
+        };
+        let live = state.rlock(version, &options);
+        let mut dead = state.rlock(version, &options);
+        let ReadLockKind::Registered(registration) = &mut dead.kind else {
+            panic!("reader lock is not registered");
+        };
+        set_pid(&state.registry, registration, dead_pid());
+
+        let options = AcquireOptions {
+            num_instances: 2,
+            grace_duration: Duration::ZERO,
+            max_sleep_duration: Duration::ZERO,
+            policy: ActiveReadersPolicy::Reset,
+            strict: true,
+        };
+        assert!(matches!(
+            state.acquire_next_idx(&options),
+            Err(SynchronizerError::ReadersStillActive { readers: 1 })
+        ));
+        assert!(!state.is_lock_valid(&dead));
+        assert!(state.is_lock_valid(&live));
+
+        state.runlock(live);
+        let acquisition = state.acquire_next_idx(&options).unwrap();
+        assert_eq!(acquisition.idx, 1);
+        assert!(!acquisition.reset);
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn reset_keeps_live_registered_readers() {
+        let state = State::new();
+        publish(&state, InstanceVersion::new(0, 1, 0).unwrap());
+        let next_version = InstanceVersion::new(1, 1, 0).unwrap();
+        let registered = state.rlock(
+            next_version,
+            &ReadOptions {
+                registered: true,
+                ..ReadOptions::default()
+            },
+        );
+        let unregistered = rlock_unregistered(&state, next_version);
+
+        thread::scope(|s| {
+            let writer = s.spawn(|| acquire(&state, Duration::ZERO, Duration::from_millis(1)));
+
+            // unregistered reader gets reset, while writer keeps waiting for the live one
+            thread::sleep(Duration::from_millis(50));
+            assert!(!writer.is_finished());
+            assert!(!state.is_lock_valid(&unregistered));
+            assert!(state.is_lock_valid(&registered));
+
+            state.runlock(registered);
+            assert_eq!(writer.join().unwrap(), (1, true));
+        });
+        state.runlock(unregistered);
+        assert_eq!(state.take_stale_unlocks(), 1);
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn leased_reader_is_evicted_once_lease_expires() {
+        let state = State::new();
+        publish(&state, InstanceVersion::new(0, 1, 0).unwrap());
+        let lease = Duration::from_millis(50);
+        let options = ReadOptions {
+            lease: Some(lease),
+            ..ReadOptions::default()
+        };
+        let lock = state.rlock(InstanceVersion::new(1, 1, 0).unwrap(), &options);
+        assert!(state.extend_lease(&lock, lease));
+
+        // leased reader is waited for past the grace period until its lease expires
+        let started_at = Instant::now();
+        let res = acquire(&state, Duration::ZERO, Duration::from_millis(1));
+        assert_eq!(res, (1, false));
+        assert!(started_at.elapsed() >= lease);
+
+        assert!(!state.is_lock_valid(&lock));
+        assert!(!state.extend_lease(&lock, lease));
+
+        // lease outlasting grace period doesn't let writer wait for it longer than needed
+        let lock = state.rlock(InstanceVersion::new(1, 1, 0).unwrap(), &options);
+        let started_at = Instant::now();
+        let res = acquire(&state, Duration::from_secs(60), Duration::from_millis(1));
+        assert_eq!(res, (1, false));
+        assert!(started_at.elapsed() < Duration::from_secs(10));
+        state.runlock(lock);
+    }
+
+    #[test]
+    fn last_leaving_reader_wakes_up_writer() {
+        let state = State::new();
+        publish(&state, InstanceVersion::new(0, 1, 0).unwrap());
+        let lock = state.rlock(
+            InstanceVersion::new(1, 1, 0).unwrap(),
+            &ReadOptions::default(),
+        );
+
+        thread::scope(|s| {
+            let writer = s.spawn(|| {
+                let started_at = Instant::now();
+                let res = acquire(&state, Duration::from_secs(60), Duration::from_secs(60));
+                (res, started_at.elapsed())
+            });
+
+            thread::sleep(Duration::from_millis(50));
+            state.runlock(lock);
+
+            let (res, elapsed) = writer.join().unwrap();
+            assert_eq!(res, (1, false));
+            assert!(elapsed < Duration::from_secs(10));
+        });
+    }
+
+    #[test]
+    fn switch_version_wakes_up_waiting_reader() {
+        let state = State::new();
+        let last = InstanceVersion::new(0, 1, 0).unwrap();
+        let next = InstanceVersion::new(1, 1, 1).unwrap();
+        publish(&state, last);
+        assert_eq!(
+            state
+                .wait_for_change(last, Duration::from_millis(10))
+                .unwrap(),
+            None
+        );
+
+        thread::scope(|s| {
+            let reader = s.spawn(|| {
+                let started_at = Instant::now();
+                let res = state.wait_for_change(last, Duration::from_secs(60));
+                (res.unwrap(), started_at.elapsed())
+            });
+
+            thread::sleep(Duration::from_millis(50));
+            publish(&state, next);
+
+            let (res, elapsed) = reader.join().unwrap();
+            assert_eq!(res, Some(next));
+            assert!(elapsed < Duration::from_secs(10));
+        });
+    }
 }
@@ -0,0 +1,166 @@
+//! The `stream` module provides asynchronous notifications about data changes for readers running
+//! within [tokio](https://tokio.rs/) runtime.
+//!
+//! Publications of the writer are watched by a single background thread per state mapping, which
+//! is woken up by the writer right after switching to a new version on Linux and fans the new
+//! number of version switches out to all the `VersionStream`s of the mapping, so that waiting for
+//! changes never blocks the executor nor occupies a thread of tokio blocking pool.
+use futures_core::Stream;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex, PoisonError, Weak};
+use std::task::{ready, Context, Poll};
+use std::thread;
+use std::time::Duration;
+use tokio::sync::watch;
+
+use crate::instance::InstanceVersion;
+use crate::state::SharedState;
+use crate::synchronizer::SynchronizerError;
+use crate::synchronizer::SynchronizerError::*;
+
+/// Maximum period the watcher thread waits for a publication before checking whether any
+/// `VersionStream` is still subscribed
+const WATCHER_IDLE_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// Publication watcher of a state mapping, shared by all the `VersionStream`s of the mapping.
+///
+/// The watcher thread is started by the first subscriber and stops once no subscribers are left.
+#[derive(Default)]
+pub(crate) struct PublicationWatcher {
+    /// Sender of version switch counts owned by the running watcher thread, if any
+    sender: Mutex<Weak<watch::Sender<u32>>>,
+}
+
+impl PublicationWatcher {
+    /// Subscribes to publications of given `state`, which must be the mapping owning this
+    /// watcher, starting the watcher thread unless it's running already
+    pub(crate) fn subscribe(&self, state: &SharedState) -> watch::Receiver<u32> {
+        let mut sender = self.sender.lock().unwrap_or_else(PoisonError::into_inner);
+        if let Some(sender) = sender.upgrade() {
+            return sender.subscribe();
+        }
+
+        let (new_sender, receiver) = watch::channel(state.switches());
+        let new_sender = Arc::new(new_sender);
+        *sender = Arc::downgrade(&new_sender);
+        let state = state.clone();
+        thread::Builder::new()
+            .name("mmap-sync-watcher".into())
+            .spawn(move || watch_publications(state, new_sender))
+            .expect("failed to spawn publication watcher thread");
+        receiver
+    }
+}
+
+/// Sends version switch counts of `state` via `sender` until no receivers are left
+fn watch_publications(state: SharedState, sender: Arc<watch::Sender<u32>>) {
+    let mut switches = *sender.borrow();
+    loop {
+        let current = state.wait_for_switch(switches, WATCHER_IDLE_TIMEOUT);
+        if current != switches {
+            switches = current;
+            sender.send_replace(switches);
+        }
+
+        // receivers only subscribe while holding the lock, so none can be missed here
+        let mut watcher = state
+            .watcher()
+            .sender
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        if sender.receiver_count() == 0 {
+            *watcher = Weak::new();
+            return;
+        }
+    }
+}
+
+/// Future resolving once number of version switches changes, handing the receiver back
+type Changed = Pin<Box<dyn Future<Output = (watch::Receiver<u32>, bool)> + Send>>;
+
+/// Returns future resolving once `receiver` observes a new number of version switches, or with
+/// `false` once the watcher is gone
+fn changed(mut receiver: watch::Receiver<u32>) -> Changed {
+    Box::pin(async move {
+        let res = receiver.changed().await;
+        (receiver, res.is_ok())
+    })
+}
+
+/// Stream of `InstanceVersion`s published by the writer, created by `Synchronizer::changes`.
+///
+/// Stream yields current version first, unless it matches the version the stream was created
+/// with, and then every next version observed. Versions published in quick succession are
+/// coalesced, so only the latest of them is yielded. Stream ends only if the background thread
+/// watching publications stops unexpectedly, e.g. by panicking.
+pub struct VersionStream {
+    state: SharedState,
+    last: Option<InstanceVersion>,
+    /// Pending wait for a new number of version switches sent by the watcher
+    changed: Changed,
+}
+
+impl VersionStream {
+    /// Creates new `VersionStream` over given `state`, skipping `last` version
+    pub(crate) fn new(state: SharedState, last: Option<InstanceVersion>) -> Self {
+        // subscribe before loading the version, so that no version switch is missed
+        let changed = changed(state.subscribe());
+        VersionStream {
+            state,
+            last,
+            changed,
+        }
+    }
+}
+
+impl Stream for VersionStream {
+    type Item = Result<InstanceVersion, SynchronizerError>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+        loop {
+            match this.state.version() {
+                Ok(version) if this.last != Some(version) => {
+                    this.last = Some(version);
+                    return Poll::Ready(Some(Ok(version)));
+                }
+                // keep waiting until the writer publishes the first version
+                Ok(_) | Err(UninitializedState) => {}
+                Err(err) => return Poll::Ready(Some(Err(err))),
+            }
+
+            match ready!(this.changed.as_mut().poll(cx)) {
+                (receiver, true) => this.changed = changed(receiver),
+                // watcher exited or panicked without waiting for receivers to be dropped
+                (_, false) => return Poll::Ready(None),
+            }
+        }
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use crate::locks::LockDisabled;
+    use crate::state::StateContainer;
+    use futures_core::Stream;
+    use std::fs;
+    use std::future::poll_fn;
+
+    #[tokio::test]
+    async fn stream_ends_once_watcher_stops() {
+        static PATH: &str = "/tmp/stream_watcher_stopped";
+        fs::remove_file(PATH.to_owned() + "_state").unwrap_or_default();
+        let mut state_container = StateContainer::<LockDisabled>::new(PATH.as_ref());
+        state_container.state::<true>(true).unwrap();
+        let mut changes = VersionStream::new(state_container.shared_state().unwrap(), None);
+
+        // replace the receiver with one whose watcher is gone, as if the watcher thread panicked
+        let (sender, receiver) = watch::channel(0);
+        drop(sender);
+        changes.changed = changed(receiver);
+        let next = poll_fn(|cx| Pin::new(&mut changes).poll_next(cx)).await;
+        assert!(next.is_none());
+    }
+}
@@ -4,23 +4,35 @@
 //!
 //! Furthermore, with the aid of the [rkyv](https://rkyv.org/) library, `Synchronizer` can perform zero-copy deserialization, reducing time and memory usage when accessing data.
 use std::ffi::OsStr;
+#[cfg(feature = "tokio")]
+use std::future::poll_fn;
 use std::hash::{BuildHasher, BuildHasherDefault, Hasher};
-use std::time::Duration;
+use std::ops::Deref;
+#[cfg(feature = "tokio")]
+use std::pin::Pin;
+use std::time::{Duration, SystemTime};
 
 use bytecheck::CheckBytes;
+#[cfg(feature = "tokio")]
+use futures_core::Stream;
 use rkyv::ser::serializers::{AlignedSerializer, AllocSerializer};
 use rkyv::ser::Serializer;
 use rkyv::validation::validators::DefaultValidator;
-use rkyv::{archived_root, check_archived_root, AlignedVec, Archive, Serialize};
+use rkyv::{archived_root, check_archived_root, AlignedVec, Archive, Archived, Serialize};
 use thiserror::Error;
 use wyhash::WyHash;
 
 use crate::data::DataContainer;
-use crate::guard::{ReadGuard, ReadResult};
-use crate::instance::InstanceVersion;
-use crate::locks::{LockDisabled, WriteLockStrategy};
-use crate::state::StateContainer;
+use crate::guard::{OwnedReadResult, ReadGuard, ReadResult};
+use crate::heartbeat::Heartbeat;
+use crate::instance::{InstanceVersion, MAX_DATA_INSTANCES};
+use crate::locks::{ExclusiveLockStrategy, LockDisabled, LockMode, WriteLockStrategy};
+use crate::metadata::Metadata;
+use crate::state::{AcquireOptions, ReadOptions, State, StateContainer, StateLayout};
+#[cfg(feature = "tokio")]
+use crate::stream::VersionStream;
 use crate::synchronizer::SynchronizerError::*;
+use crate::utils::{self, FingerprintCache};
 
 /// `Synchronizer` is a concurrency primitive that manages data access between a single writer process and multiple reader processes.
 ///
@@ -30,7 +42,8 @@ use crate::synchronizer::SynchronizerError::*;
 ///   - `H` - hasher used for checksum calculation
 ///   - `WL` - optional write locking to prevent multiple writers. (default [`LockDisabled`])
 ///   - `N` - serializer scratch space size
-///   - `SD` - sleep duration in nanoseconds used by writer during lock acquisition (default 1s)
+///   - `SD` - maximum sleep duration in nanoseconds used by writer while waiting for readers
+///     to leave during lock acquisition, as the last leaving reader wakes writer up (default 1s)
 pub struct Synchronizer<
     H: Hasher + Default = WyHash,
     WL = LockDisabled,
@@ -45,6 +58,137 @@ pub struct Synchronizer<
     build_hasher: BuildHasherDefault<H>,
     /// Re-usable buffer for serialization
     serialize_buffer: Option<AlignedVec>,
+    /// Whether data checksum is verified on every read
+    verify_checksum: bool,
+    /// Whether type of the data is checked on every read
+    type_check: bool,
+    /// Fingerprint of the type read by this synchronizer
+    fingerprints: FingerprintCache,
+    /// Maximum age of data returned by `read`
+    max_age: Option<Duration>,
+    /// Number of data instances written in turns by the writer
+    data_instances: usize,
+    /// Handling of readers still active once grace period expires
+    active_readers_policy: ActiveReadersPolicy,
+    /// Whether the writer enables strict mode
+    strict: bool,
+    /// Whether read results assert that they are still valid on drop in debug builds
+    assert_valid_reads: bool,
+    /// Whether readers are counted in readers counter shards of their threads
+    sharded_readers: bool,
+    /// Whether readers are recorded within the registry of readers
+    registered_readers: bool,
+}
+
+/// `Synchronizer` write result
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct WriteResult {
+    size: usize,
+    reset: bool,
+    stale_unlocks: u32,
+    generation: u64,
+    evicted_readers: u32,
+}
+
+impl WriteResult {
+    /// Returns number of bytes written to the data file
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
+    /// Indicates whether the reader counter was reset due to a reader exiting without
+    /// decrementing it
+    pub fn is_reset(&self) -> bool {
+        self.reset
+    }
+
+    /// Returns number of reader unlocks ignored since the previous write, because the reader
+    /// counter had been reset while those readers were still holding their locks
+    pub fn stale_unlocks(&self) -> u32 {
+        self.stale_unlocks
+    }
+
+    /// Returns publication generation of the written data
+    pub fn generation(&self) -> u64 {
+        self.generation
+    }
+
+    /// Returns number of readers evicted from the written data file, either because they died
+    /// without unlocking or because the reader counter was reset
+    pub fn evicted_readers(&self) -> u32 {
+        self.evicted_readers
+    }
+}
+
+/// Ensures that the data locked by `guard` was written as the same type `T` if `fingerprints`
+/// are given and, if `max_age` is set, that it's fresh enough
+pub(crate) fn check_header<T: Archive>(
+    guard: &ReadGuard<impl Deref<Target = State>>,
+    fingerprints: Option<&FingerprintCache>,
+    max_age: Option<Duration>,
+) -> Result<(), SynchronizerError> {
+    // ensure that the data was written as the same type `T` before interpreting it
+    if let Some(fingerprints) = fingerprints {
+        if guard.header().type_fingerprint() != fingerprints.type_fingerprint::<T>() {
+            return Err(TypeMismatch);
+        }
+    }
+
+    // ensure that the data is fresh enough if maximum age is set
+    if let Some(max_age) = max_age {
+        let age = guard.header().metadata().age();
+        if age > max_age {
+            return Err(StaleData { age });
+        }
+    }
+
+    Ok(())
+}
+
+/// Fetches entity of type `T` from `data` locked by `guard`, verifying data checksum first if
+/// `build_hasher` is given.
+///
+/// # Safety
+///
+/// Same as `Synchronizer::read`.
+pub(crate) unsafe fn archived_entity<'d, T, H>(
+    guard: &ReadGuard<impl Deref<Target = State>>,
+    data: &'d [u8],
+    build_hasher: Option<&BuildHasherDefault<H>>,
+    check_bytes: bool,
+) -> Result<&'d Archived<T>, SynchronizerError>
+where
+    T: Archive,
+    T::Archived: for<'b> CheckBytes<DefaultValidator<'b>>,
+    H: Hasher + Default,
+{
+    // verify full-width data checksum to detect corrupted data files
+    if let Some(build_hasher) = build_hasher {
+        let mut hasher = build_hasher.build_hasher();
+        hasher.write(data);
+        if hasher.finish() != guard.header().checksum() {
+            return Err(ChecksumMismatch);
+        }
+    }
+
+    // fetch entity from data using zero-copy deserialization
+    match check_bytes {
+        false => Ok(archived_root::<T>(data)),
+        true => check_archived_root::<T>(data).map_err(|_| FailedEntityRead),
+    }
+}
+
+/// Handling of readers still holding the next data file once `grace_duration` of a write
+/// expires, set via `Synchronizer::with_active_readers_policy`
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ActiveReadersPolicy {
+    /// Reset the reader counter, unless all remaining readers are known to be alive
+    #[default]
+    Reset,
+    /// Fail the write with `ReadersStillActive` error, never risking corruption of slow readers
+    Fail,
+    /// Keep waiting for readers to leave indefinitely, ignoring `grace_duration`
+    Wait,
 }
 
 /// `SynchronizerError` enumerates all possible errors returned by this library.
@@ -73,9 +217,60 @@ pub enum SynchronizerError {
     /// The instance version parameters were invalid.
     #[error("invalid instance version params")]
     InvalidInstanceVersionParams,
+    /// Readers still hold the next data file and the write policy forbids resetting them.
+    #[error("{readers} readers still active")]
+    ReadersStillActive {
+        /// Number of readers holding the next data file.
+        readers: u32,
+    },
     /// Write locking is enabled and the lock is held by another writer.
     #[error("write blocked by conflicting lock")]
     WriteLockConflict,
+    /// The version kept changing while registering as a reader.
+    #[error("version changed while registering reader")]
+    VersionChangedDuringRead,
+    /// The data checksum doesn't match the checksum recorded by the writer.
+    #[error("data checksum mismatch")]
+    ChecksumMismatch,
+    /// The type of the entity being read doesn't match the type written by the writer.
+    #[error("entity type mismatch")]
+    TypeMismatch,
+    /// The data was published longer ago than the maximum age set via `with_max_age`.
+    #[error("stale data published {age:?} ago")]
+    StaleData {
+        /// Time elapsed since the data was published.
+        age: Duration,
+    },
+    /// The fencing epoch of the writer has been superseded by another writer acquiring the lock.
+    #[error("writer fenced out: epoch {epoch} superseded by {current}")]
+    FencedOut {
+        /// Fencing epoch of the writer.
+        epoch: u64,
+        /// Current fencing epoch stored within the state.
+        current: u64,
+    },
+    /// Safe read was requested, but strict mode has not been enabled by the writer.
+    #[error("strict mode disabled")]
+    StrictModeDisabled,
+    /// The state file is not a valid state file.
+    #[error("invalid state file")]
+    InvalidStateFile,
+    /// The state file was created with an incompatible layout version.
+    #[error("incompatible state layout version: expected {expected}, found {found}")]
+    IncompatibleStateLayout {
+        /// Layout version supported by this library.
+        expected: u32,
+        /// Layout version found in the state file.
+        found: u32,
+    },
+    /// The state file was created on a platform with different endianness or pointer width.
+    #[error("state file created on incompatible platform")]
+    IncompatibleStatePlatform,
+    /// The background thread watching publications has stopped.
+    #[cfg(feature = "tokio")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "tokio")))]
+    #[error("publication watcher stopped")]
+    WatcherStopped,
 }
 
 impl Synchronizer {
@@ -97,14 +292,136 @@ where
             data_container: DataContainer::new(path_prefix),
             build_hasher: BuildHasherDefault::default(),
             serialize_buffer: Some(AlignedVec::new()),
+            verify_checksum: false,
+            type_check: true,
+            fingerprints: FingerprintCache::default(),
+            max_age: None,
+            data_instances: 2,
+            active_readers_policy: ActiveReadersPolicy::default(),
+            strict: false,
+            assert_valid_reads: false,
+            sharded_readers: false,
+            registered_readers: false,
         }
     }
 
+    /// Enables or disables verification of data checksum on every read.
+    ///
+    /// When enabled, `read` re-hashes the whole data using hasher `H` and compares the result
+    /// with full-width checksum recorded by the writer, returning `ChecksumMismatch` error on
+    /// mismatch. This allows detecting data files corrupted e.g. by bit rot or partial writes on
+    /// disk-backed storage, at the cost of reading all the data on every read.
+    pub fn with_checksum_verification(mut self, enabled: bool) -> Self {
+        self.verify_checksum = enabled;
+        self
+    }
+
+    /// Enables or disables check of the type of data on every read (enabled by default).
+    ///
+    /// When enabled, `read` compares fingerprint of the type it reads with the fingerprint of the
+    /// type written by the writer, returning `TypeMismatch` error if they differ. Fingerprints
+    /// cover the name of the type, so reading data written as a type mirroring the layout of the
+    /// read type under another name requires disabling the check.
+    ///
+    /// The check is heuristic: fingerprints combine the name of the type with size and alignment
+    /// of its archived form only, not with names, types and offsets of its fields. Types of the
+    /// same name and size, e.g. two versions of a struct with reordered fields, are therefore not
+    /// told apart, so `read` must keep `check_bytes` enabled whenever the type of data may drift
+    /// between the writer and readers.
+    pub fn with_type_check(mut self, enabled: bool) -> Self {
+        self.type_check = enabled;
+        self
+    }
+
+    /// Sets number of data instances, each stored in a separate data file, which the writer
+    /// writes in turns (default 2).
+    ///
+    /// Writer picks any data instance without readers other than the current one, so with more
+    /// than two data instances it doesn't have to wait for slow readers as long as at least one
+    /// older data instance is free. Readers don't need to be configured with the same number.
+    ///
+    /// # Panics
+    /// Panics if `data_instances` is less than 2 or greater than 8.
+    pub fn with_data_instances(mut self, data_instances: usize) -> Self {
+        assert!(
+            (2..=MAX_DATA_INSTANCES).contains(&data_instances),
+            "number of data instances must be between 2 and {MAX_DATA_INSTANCES}"
+        );
+        self.data_instances = data_instances;
+        self
+    }
+
+    /// Sets handling of readers still holding the next data file once `grace_duration` of
+    /// a write expires (default [`ActiveReadersPolicy::Reset`]).
+    pub fn with_active_readers_policy(mut self, policy: ActiveReadersPolicy) -> Self {
+        self.active_readers_policy = policy;
+        self
+    }
+
+    /// Sets mode of write lock acquisition, which only applies to lock strategies actually
+    /// locking, e.g. [`SingleWriter`](crate::locks::SingleWriter).
+    ///
+    /// Defaults to [`WriteLockStrategy::DEFAULT_LOCK_MODE`] of the lock strategy `WL`: with
+    /// [`SingleWriter`](crate::locks::SingleWriter) and [`OfdWriter`](crate::locks::OfdWriter)
+    /// writes fail with `WriteLockConflict` right away if the lock is held by another writer
+    /// ([`LockMode::NonBlocking`]), while with [`Failover`](crate::locks::Failover) writes wait
+    /// for the lock to be released ([`LockMode::Blocking`]).
+    pub fn with_lock_mode(mut self, lock_mode: LockMode) -> Self {
+        self.state_container.set_lock_mode(lock_mode);
+        self
+    }
+
+    /// Enables assertion that `ReadResult`s are still valid when dropped, in debug builds only.
+    ///
+    /// Dropping a result whose data file was reset by the writer in the meantime then panics,
+    /// helping to find readers which outlive the `grace_duration` (see
+    /// `ReadResult::is_still_valid`).
+    pub fn with_validity_assertion(mut self, enabled: bool) -> Self {
+        self.assert_valid_reads = enabled;
+        self
+    }
+
+    /// Enables counting of readers in shards of readers counters picked per reader thread.
+    ///
+    /// By default every read increments and decrements the same readers counter shared by all
+    /// the readers of a data file, which becomes a point of cache-line contention with many reader
+    /// threads. In sharded mode readers are spread across counters padded to separate cache
+    /// lines, which are summed up by the writer. Readers with and without sharded mode can be
+    /// mixed freely.
+    pub fn with_sharded_readers(mut self, enabled: bool) -> Self {
+        self.sharded_readers = enabled;
+        self
+    }
+
+    /// Enables recording of readers within the registry of readers stored in the state file,
+    /// along with their process id.
+    ///
+    /// By default readers are only counted, so the writer can't tell whether they are still alive
+    /// and resets them once `grace_duration` of a write expires. Registered readers which died
+    /// without unlocking are evicted right away instead, while registered readers which are still
+    /// alive are never reset: the writer keeps waiting for them to finish. Registration makes
+    /// reads somewhat slower, and is only available on Linux with `/proc` mounted for the pid
+    /// namespace of the reader. Readers which can't be registered, e.g. when too many readers
+    /// are active, are counted as usual.
+    pub fn with_registered_readers(mut self, enabled: bool) -> Self {
+        self.registered_readers = enabled;
+        self
+    }
+
+    /// Sets maximum age of data returned by `read`.
+    ///
+    /// When set, `read` returns `StaleData` error if the current data was published longer than
+    /// `max_age` ago according to the local wall clock, allowing to detect stale data feeds.
+    pub fn with_max_age(mut self, max_age: Duration) -> Self {
+        self.max_age = Some(max_age);
+        self
+    }
+
     /// Writes a given `entity` into the next available data file.
     ///
-    /// Returns the number of bytes written to the data file and a boolean flag, for diagnostic
-    /// purposes, indicating whether the reader counter was reset due to a reader exiting without
-    /// decrementing it.
+    /// Returns the number of bytes written to the data file along with diagnostic information:
+    /// whether the reader counter was reset due to a reader exiting without decrementing it, and
+    /// how many unlocks of readers outliving such a reset were ignored since the previous write.
     ///
     /// # Parameters
     /// - `entity`: The entity to be written to the data file.
@@ -114,14 +431,49 @@ where
     ///   elapsed, if there are still active readers, the reader count is reset to 0 to restore
     ///   synchronization state.
     ///
+    ///   Readers created with `with_registered_readers` are recorded in a registry within the
+    ///   state file instead. Locks of registered readers whose process has died are released
+    ///   without waiting for the `grace_duration`, while registered readers which are still
+    ///   alive, or belong to another pid namespace, are never reset: the writer keeps waiting
+    ///   for them to finish instead. The reset only applies to readers which are not registered.
+    ///
+    ///   Handling of readers still active once `grace_duration` elapses can be changed via
+    ///   `with_active_readers_policy`, e.g. to fail the write instead of resetting them.
+    ///
     /// # Returns
-    /// A result containing a tuple of the number of bytes written and a boolean indicating whether
-    /// the reader count was reset, or a `SynchronizerError` if the operation fails.
+    /// A result containing a `WriteResult`, or a `SynchronizerError` if the operation fails.
     pub fn write<T>(
         &'a mut self,
         entity: &T,
         grace_duration: Duration,
-    ) -> Result<(usize, bool), SynchronizerError>
+    ) -> Result<WriteResult, SynchronizerError>
+    where
+        T: Serialize<AllocSerializer<N>>,
+        T::Archived: for<'b> CheckBytes<DefaultValidator<'b>>,
+    {
+        let options = self.acquire_options(grace_duration, self.active_readers_policy);
+        self.write_entity(entity, options)
+    }
+
+    /// Writes a given `entity` into the next available data file without waiting for readers.
+    ///
+    /// Fails with `ReadersStillActive` error right away if readers still hold all the data files
+    /// available for writing.
+    pub fn try_write<T>(&'a mut self, entity: &T) -> Result<WriteResult, SynchronizerError>
+    where
+        T: Serialize<AllocSerializer<N>>,
+        T::Archived: for<'b> CheckBytes<DefaultValidator<'b>>,
+    {
+        let options = self.acquire_options(Duration::ZERO, ActiveReadersPolicy::Fail);
+        self.write_entity(entity, options)
+    }
+
+    /// Serializes given `entity` and writes it into the next data file acquired with `options`
+    fn write_entity<T>(
+        &'a mut self,
+        entity: &T,
+        options: AcquireOptions,
+    ) -> Result<WriteResult, SynchronizerError>
     where
         T: Serialize<AllocSerializer<N>>,
         T::Archived: for<'b> CheckBytes<DefaultValidator<'b>>,
@@ -135,67 +487,162 @@ where
             Default::default(),
             Default::default(),
         );
-        let _ = serializer
-            .serialize_value(entity)
-            .map_err(|_| FailedEntityWrite)?;
+        if serializer.serialize_value(entity).is_err() {
+            // serializer owns the buffer, so provide a new one for potential reuse
+            self.serialize_buffer.replace(AlignedVec::new());
+            return Err(FailedEntityWrite);
+        }
         let data = serializer.into_serializer().into_inner();
 
         // ensure that serialized bytes can be deserialized back to `T` struct successfully
-        check_archived_root::<T>(&data).map_err(|_| FailedEntityRead)?;
+        // and write them into the next available data file
+        let res = check_archived_root::<T>(&data)
+            .map_err(|_| FailedEntityRead)
+            .and_then(|_| {
+                Self::publish::<T>(
+                    &mut self.state_container,
+                    &mut self.data_container,
+                    &self.build_hasher,
+                    &data,
+                    &options,
+                )
+            });
 
-        // fetch current state from mapped memory
-        let state = self.state_container.state::<true>(true)?;
-
-        // calculate data checksum
-        let mut hasher = self.build_hasher.build_hasher();
-        hasher.write(&data);
-        let checksum = hasher.finish();
-
-        // acquire next available data file idx and write data to it
-        let acquire_sleep_duration = Duration::from_nanos(SD);
-        let (new_idx, reset) = state.acquire_next_idx(grace_duration, acquire_sleep_duration);
-        let new_version = InstanceVersion::new(new_idx, data.len(), checksum)?;
-        let size = self.data_container.write(&data, new_version)?;
-
-        // switch readers to new version
-        state.switch_version(new_version);
-
-        // Restore buffer for potential reuse
+        // Restore buffer for potential reuse, even if the write has failed
         self.serialize_buffer.replace(data);
 
-        Ok((size, reset))
+        res
     }
 
     /// Write raw data bytes representing type `T` into the next available data file.
-    /// Returns number of bytes written to data file and a boolean flag, for diagnostic purposes,
-    /// indicating that we have reset our readers counter after a reader died without decrementing it.
+    /// Returns `WriteResult` with number of bytes written to data file and diagnostic information
+    /// about reader counter resets, same as `write`.
     pub fn write_raw<T>(
         &'a mut self,
         data: &[u8],
         grace_duration: Duration,
-    ) -> Result<(usize, bool), SynchronizerError>
+    ) -> Result<WriteResult, SynchronizerError>
     where
         T: Serialize<AllocSerializer<N>>,
         T::Archived: for<'b> CheckBytes<DefaultValidator<'b>>,
     {
+        let options = self.acquire_options(grace_duration, self.active_readers_policy);
+        Self::publish::<T>(
+            &mut self.state_container,
+            &mut self.data_container,
+            &self.build_hasher,
+            data,
+            &options,
+        )
+    }
+
+    /// Returns options of data file acquisition for writing with given `grace_duration` and
+    /// active readers `policy`
+    fn acquire_options(
+        &self,
+        grace_duration: Duration,
+        policy: ActiveReadersPolicy,
+    ) -> AcquireOptions {
+        AcquireOptions {
+            num_instances: self.data_instances,
+            grace_duration,
+            max_sleep_duration: Duration::from_nanos(SD),
+            policy,
+            strict: self.strict,
+        }
+    }
+
+    /// Write `data` representing type `T` into the next data file acquired with `options` and
+    /// switch readers to it
+    fn publish<T: Archive>(
+        state_container: &'a mut StateContainer<WL>,
+        data_container: &mut DataContainer,
+        build_hasher: &BuildHasherDefault<H>,
+        data: &[u8],
+        options: &AcquireOptions,
+    ) -> Result<WriteResult, SynchronizerError> {
         // fetch current state from mapped memory
-        let state = self.state_container.state::<true>(true)?;
+        let (state, fencing_epoch) = state_container.write_state(true)?;
 
         // calculate data checksum
-        let mut hasher = self.build_hasher.build_hasher();
+        let mut hasher = build_hasher.build_hasher();
         hasher.write(data);
         let checksum = hasher.finish();
 
-        // acquire next available data file idx and write data to it
-        let acquire_sleep_duration = Duration::from_nanos(SD);
-        let (new_idx, reset) = state.acquire_next_idx(grace_duration, acquire_sleep_duration);
-        let new_version = InstanceVersion::new(new_idx, data.len(), checksum)?;
-        let size = self.data_container.write(data, new_version)?;
+        // acquire next available data file idx and write data to it, unless another writer took
+        // over while waiting for readers
+        let current = state.version().ok();
+        let acquisition = state.acquire_next_idx(options)?;
+        state.check_fencing_epoch(fencing_epoch)?;
+        let new_version = InstanceVersion::new(acquisition.idx, data.len(), checksum)?;
+        let size = data_container.write(data, new_version)?;
 
         // switch readers to new version
-        state.switch_version(new_version);
+        let generation = state.switch_version(
+            current,
+            new_version,
+            checksum,
+            utils::type_fingerprint::<T>(),
+            &Metadata::current(),
+            fencing_epoch,
+        )?;
+
+        Ok(WriteResult {
+            size,
+            reset: acquisition.reset,
+            stale_unlocks: state.take_stale_unlocks(),
+            generation,
+            evicted_readers: acquisition.evicted,
+        })
+    }
 
-        Ok((size, reset))
+    /// Releases write lock if it is held, allowing another writer to take over without tearing
+    /// down this `Synchronizer`. The lock is acquired again on the next write, unless another
+    /// writer acquired fenced lock in the meantime, in which case the write fails with
+    /// `FencedOut` error.
+    ///
+    /// Background heartbeat started via `spawn_heartbeat` stops as well, since this writer no
+    /// longer owns the state.
+    pub fn release_write_lock(&mut self) -> Result<(), SynchronizerError> {
+        self.state_container.release_write_lock()
+    }
+
+    /// Records writer heartbeat within the state, signalling readers that the writer is alive
+    /// even when it has no new data to write. Every write records a heartbeat as well.
+    pub fn heartbeat(&'a mut self) -> Result<(), SynchronizerError> {
+        // fetch current state from mapped memory
+        let state = self.state_container.state::<true>(true)?;
+
+        state.heartbeat();
+        Ok(())
+    }
+
+    /// Spawns a background thread recording writer heartbeat every `interval`, until the
+    /// returned `Heartbeat` handle is dropped.
+    ///
+    /// The thread maps the state file on its own, so that it never contends with this
+    /// `Synchronizer`. Write lock, if enabled, is acquired before spawning the thread, and
+    /// the first heartbeat is recorded right away. The thread stops once the write lock is
+    /// released via `release_write_lock` or this `Synchronizer` is dropped, or once this writer
+    /// is fenced out by another one.
+    pub fn spawn_heartbeat(
+        &'a mut self,
+        interval: Duration,
+    ) -> Result<Heartbeat, SynchronizerError> {
+        let mut state_container = self.state_container.detached();
+        let released = self.state_container.released();
+        let (state, fencing_epoch) = self.state_container.write_state(true)?;
+        state.heartbeat();
+
+        // ensure that the state can be mapped before handing it over to the thread
+        state_container.state::<false>(false)?;
+
+        Ok(Heartbeat::spawn(
+            state_container,
+            interval,
+            fencing_epoch,
+            released,
+        ))
     }
 
     /// Reads and returns an `entity` struct from mapped memory wrapped in `ReadGuard`.
@@ -205,6 +652,13 @@ where
     ///   validate that the underlying bytes can be safely read for type `T`. This check, however,
     ///   can be slow.
     ///
+    /// Fingerprint of type `T` is compared with the fingerprint of the type written by the writer,
+    /// returning `TypeMismatch` error if they differ, unless disabled via `with_type_check`. Data
+    /// checksum is additionally verified if enabled via `with_checksum_verification`. Type check
+    /// is heuristic and doesn't tell apart types of the same name and archived size, so
+    /// `check_bytes` must stay enabled whenever the type of data may drift, as described for
+    /// `with_type_check`.
+    ///
     /// # Safety
     ///
     /// This method is marked as unsafe due to the potential for memory corruption if the returned
@@ -224,27 +678,181 @@ where
         T: Archive,
         T::Archived: for<'b> CheckBytes<DefaultValidator<'b>>,
     {
-        // fetch current state from mapped memory
-        let state = self.state_container.state::<false>(false)?;
+        self.read_entity::<T>(check_bytes, None, false)
+    }
 
-        // fetch current version
-        let version = state.version()?;
+    /// Reads and returns an `entity` struct from mapped memory wrapped in `ReadGuard`, holding
+    /// a lease for given `lease` duration.
+    ///
+    /// Writer honors the lease instead of its `grace_duration`: it waits for the result to be
+    /// dropped until the lease expires even if its grace period is shorter, and may reset the
+    /// result right after the lease expires even if its grace period is longer. The lease can be
+    /// extended via `ReadResult::extend_lease`. Leases are only honored by writers resetting
+    /// readers via `ActiveReadersPolicy::Reset`, while writers failing via
+    /// `ActiveReadersPolicy::Fail` or `try_write` fail once their grace period expires regardless
+    /// of leases.
+    ///
+    /// Leases are recorded in the registry of readers, which is only supported on Linux, so
+    /// `grace_duration` still applies on other platforms, or if the reader can't be registered,
+    /// e.g. when too many readers are active, in which case `ReadResult::extend_lease` returns
+    /// `false`.
+    ///
+    /// # Safety
+    ///
+    /// Same as `read`, except that the result must be dropped before its lease expires.
+    pub unsafe fn read_with_lease<T>(
+        &'a mut self,
+        check_bytes: bool,
+        lease: Duration,
+    ) -> Result<ReadResult<'a, T>, SynchronizerError>
+    where
+        T: Archive,
+        T::Archived: for<'b> CheckBytes<DefaultValidator<'b>>,
+    {
+        self.read_entity::<T>(check_bytes, Some(lease), false)
+    }
 
-        // create and lock state guard for reading
-        let guard = ReadGuard::new(state, version);
+    /// Reads and returns an `entity` struct from mapped memory wrapped in `OwnedReadResult`,
+    /// which doesn't borrow this `Synchronizer`.
+    ///
+    /// The result holds the reader lock along with reference-counted mappings of the state and
+    /// data until dropped, so it can be stored in a struct or moved to another thread, the same
+    /// way as `SharedReader::read_owned` does. The state is mapped once more on the first call,
+    /// independently of this `Synchronizer`.
+    ///
+    /// # Safety
+    ///
+    /// Same as `read`.
+    pub unsafe fn read_owned<T>(
+        &mut self,
+        check_bytes: bool,
+    ) -> Result<OwnedReadResult<T>, SynchronizerError>
+    where
+        T: Archive,
+        T::Archived: for<'b> CheckBytes<DefaultValidator<'b>>,
+    {
+        self.read_owned_entity::<T>(check_bytes, None)
+    }
 
-        // fetch data for current version from mapped memory
-        let (data, switched) = self.data_container.data(version)?;
+    /// Reads and returns an `entity` struct from mapped memory wrapped in `OwnedReadResult`,
+    /// holding a lease for given `lease` duration the same way as `read_with_lease` does.
+    ///
+    /// # Safety
+    ///
+    /// Same as `read_with_lease`.
+    pub unsafe fn read_owned_with_lease<T>(
+        &mut self,
+        check_bytes: bool,
+        lease: Duration,
+    ) -> Result<OwnedReadResult<T>, SynchronizerError>
+    where
+        T: Archive,
+        T::Archived: for<'b> CheckBytes<DefaultValidator<'b>>,
+    {
+        self.read_owned_entity::<T>(check_bytes, Some(lease))
+    }
+
+    /// Reads an `entity` struct from mapped memory holding optional `lease` into a result owning
+    /// the mappings.
+    ///
+    /// # Safety
+    ///
+    /// Same as `read`.
+    unsafe fn read_owned_entity<T>(
+        &mut self,
+        check_bytes: bool,
+        lease: Option<Duration>,
+    ) -> Result<OwnedReadResult<T>, SynchronizerError>
+    where
+        T: Archive,
+        T::Archived: for<'b> CheckBytes<DefaultValidator<'b>>,
+    {
+        // fetch state mapped independently of this synchronizer, which is moved into the result
+        let state = self.state_container.shared_state()?;
+
+        // create and lock state guard for reading current version
+        let options = ReadOptions {
+            lease,
+            sharded: self.sharded_readers,
+            registered: self.registered_readers,
+        };
+        let guard = ReadGuard::new(state, &options, self.assert_valid_reads)?;
+        let fingerprints = self.type_check.then_some(&self.fingerprints);
+        check_header::<T>(&guard, fingerprints, self.max_age)?;
+
+        // fetch data for current version from mapping, which is moved into the result along
+        // with the entity referring to it
+        let (mmap, switched) = self
+            .data_container
+            .shared_data(guard.version(), guard.generation())?;
+        let data = &*(&mmap[..guard.version().size()] as *const [u8]);
+        let hasher = self.verify_checksum.then_some(&self.build_hasher);
+        let entity = archived_entity::<T, H>(&guard, data, hasher, check_bytes)?;
+
+        Ok(OwnedReadResult::new(guard, entity, switched, mmap))
+    }
 
-        // fetch entity from data using zero-copy deserialization
-        let entity = match check_bytes {
-            false => archived_root::<T>(data),
-            true => check_archived_root::<T>(data).map_err(|_| FailedEntityRead)?,
+    /// Reads an `entity` struct from mapped memory holding optional `lease`, only if strict mode
+    /// is enabled when `strict_only` is set.
+    ///
+    /// # Safety
+    ///
+    /// Same as `read`, unless `strict_only` and `check_bytes` are both set.
+    unsafe fn read_entity<T>(
+        &'a mut self,
+        check_bytes: bool,
+        lease: Option<Duration>,
+        strict_only: bool,
+    ) -> Result<ReadResult<'a, T>, SynchronizerError>
+    where
+        T: Archive,
+        T::Archived: for<'b> CheckBytes<DefaultValidator<'b>>,
+    {
+        // fetch current state from mapped memory
+        let state: &State = self.state_container.state::<false>(false)?;
+        if strict_only && !state.is_strict() {
+            return Err(StrictModeDisabled);
+        }
+
+        // create and lock state guard for reading current version
+        let options = ReadOptions {
+            lease,
+            sharded: self.sharded_readers,
+            registered: self.registered_readers,
         };
+        let guard = ReadGuard::new(state, &options, self.assert_valid_reads)?;
+        let fingerprints = self.type_check.then_some(&self.fingerprints);
+        check_header::<T>(&guard, fingerprints, self.max_age)?;
+
+        // fetch data for current version from mapped memory
+        let (data, switched) = self
+            .data_container
+            .data(guard.version(), guard.generation())?;
+        let hasher = self.verify_checksum.then_some(&self.build_hasher);
+        let entity = archived_entity::<T, H>(&guard, data, hasher, check_bytes)?;
 
         Ok(ReadResult::new(guard, entity, switched))
     }
 
+    /// Safely reads and returns an `entity` struct from mapped memory wrapped in `ReadGuard`.
+    ///
+    /// Unlike `read`, this method is available only once strict mode is enabled by a writer
+    /// holding an exclusive write lock via `with_strict_mode`, returning `StrictModeDisabled`
+    /// error otherwise. As readers are never reset in strict mode, the data stays intact for as
+    /// long as the result is alive. The data is
+    /// always validated with `rkyv`'s check functionality, the same way as `read` does with
+    /// `check_bytes` enabled.
+    ///
+    /// Data files are assumed not to be modified by anything other than writers of this library.
+    pub fn safe_read<T>(&'a mut self) -> Result<ReadResult<'a, T>, SynchronizerError>
+    where
+        T: Archive,
+        T::Archived: for<'b> CheckBytes<DefaultValidator<'b>>,
+    {
+        // SAFETY: readers are never reset in strict mode and bytes are validated
+        unsafe { self.read_entity::<T>(true, None, true) }
+    }
+
     /// Returns current `InstanceVersion` stored within the state, useful for detecting
     /// whether synchronized `entity` has changed.
     pub fn version(&'a mut self) -> Result<InstanceVersion, SynchronizerError> {
@@ -254,21 +862,160 @@ where
         // fetch current version
         state.version()
     }
+
+    /// Returns current fencing epoch stored within the state, which is advanced every time
+    /// a writer acquires fenced write lock, e.g. [`Failover`](crate::locks::Failover).
+    pub fn fencing_epoch(&'a mut self) -> Result<u64, SynchronizerError> {
+        // fetch current state from mapped memory
+        let state = self.state_container.state::<false>(false)?;
+
+        Ok(state.fencing_epoch())
+    }
+
+    /// Returns publication `Metadata` of current version stored within the state, describing
+    /// when and by whom the data was published.
+    pub fn metadata(&'a mut self) -> Result<Metadata, SynchronizerError> {
+        // fetch current state from mapped memory
+        let state = self.state_container.state::<false>(false)?;
+
+        // fetch metadata of current version
+        state.metadata()
+    }
+
+    /// Returns publication generation of current version stored within the state. Generation
+    /// is incremented on every write, even when the same data is written again, so it can be used
+    /// for reliable ordering and deduplication of updates. Acquisitions of fenced write lock
+    /// advance it as well, so generations of consecutive writes are not necessarily contiguous.
+    pub fn generation(&'a mut self) -> Result<u64, SynchronizerError> {
+        // fetch current state from mapped memory
+        let state = self.state_container.state::<false>(false)?;
+
+        // fetch generation of current version
+        Ok(state.publication())
+    }
+
+    /// Returns wall-clock time of the last writer heartbeat, or `None` if there was none yet
+    pub fn last_heartbeat(&'a mut self) -> Result<Option<SystemTime>, SynchronizerError> {
+        // fetch current state from mapped memory
+        let state = self.state_container.state::<false>(false)?;
+
+        Ok(state.last_heartbeat())
+    }
+
+    /// Indicates whether the writer recorded a heartbeat or wrote data within the last
+    /// `threshold` according to the local wall clock, allowing to tell apart a writer with no
+    /// new data from a writer which is gone.
+    pub fn is_writer_alive(&'a mut self, threshold: Duration) -> Result<bool, SynchronizerError> {
+        let last_heartbeat = self.last_heartbeat()?;
+
+        Ok(last_heartbeat.is_some_and(|last_heartbeat| {
+            SystemTime::now()
+                .duration_since(last_heartbeat)
+                .unwrap_or_default()
+                <= threshold
+        }))
+    }
+
+    /// Blocks until current `InstanceVersion` stored within the state differs from `last`
+    /// version, or the `timeout` elapses.
+    ///
+    /// On Linux waiting reader is woken up by the writer right after switching to a new version,
+    /// without burning CPU in the meantime. Other platforms fall back to polling with backoff.
+    ///
+    /// # Returns
+    /// A result containing the new `InstanceVersion`, or `None` if the version hasn't changed
+    /// before the `timeout`, or a `SynchronizerError` if the operation fails.
+    pub fn wait_for_change(
+        &'a mut self,
+        last: InstanceVersion,
+        timeout: Duration,
+    ) -> Result<Option<InstanceVersion>, SynchronizerError> {
+        // fetch current state from mapped memory
+        let state = self.state_container.state::<false>(false)?;
+
+        // wait for the version to change
+        state.wait_for_change(last, timeout)
+    }
+
+    /// Waits asynchronously until current `InstanceVersion` stored within the state differs from
+    /// `last` version and returns the new version.
+    ///
+    /// The version is awaited by a background thread shared by all the change notifications of
+    /// this `Synchronizer`, the same way as `wait_for_change` does, so the executor is never
+    /// blocked. Returns `WatcherStopped` error if that thread stops unexpectedly.
+    #[cfg(feature = "tokio")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "tokio")))]
+    pub async fn changed(
+        &'a mut self,
+        last: InstanceVersion,
+    ) -> Result<InstanceVersion, SynchronizerError> {
+        // fetch state mapped independently of this synchronizer for the watcher thread
+        let state = self.state_container.shared_state()?;
+
+        // wait for the first version differing from `last`
+        let mut changes = VersionStream::new(state, Some(last));
+        match poll_fn(|cx| Pin::new(&mut changes).poll_next(cx)).await {
+            Some(res) => res,
+            None => Err(SynchronizerError::WatcherStopped),
+        }
+    }
+
+    /// Returns a `Stream` of `InstanceVersion`s published by the writer, starting with the
+    /// current one, useful for reacting to changes of synchronized `entity` asynchronously.
+    #[cfg(feature = "tokio")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "tokio")))]
+    pub fn changes(&'a mut self) -> Result<VersionStream, SynchronizerError> {
+        // fetch state mapped independently of this synchronizer for the watcher thread
+        let state = self.state_container.shared_state()?;
+
+        Ok(VersionStream::new(state, None))
+    }
+}
+
+impl<'a, H, WL, const N: usize, const SD: u64> Synchronizer<H, WL, N, SD>
+where
+    H: Hasher + Default,
+    WL: ExclusiveLockStrategy<'a>,
+{
+    /// Enables strict mode on the next write, in which live readers holding data files are never
+    /// reset nor evicted, making `safe_read` available to readers.
+    ///
+    /// Strict mode is recorded within the state and can't be disabled afterwards: every writer of
+    /// the state then fails with `ReadersStillActive` error instead of resetting readers once
+    /// `grace_duration` expires or evicting them once their leases expire, regardless of its
+    /// `ActiveReadersPolicy`. Registered readers which died without unlocking are still evicted,
+    /// while an unregistered one blocks its data file until the state file is recreated, which is
+    /// best mitigated by using more than two data instances via `with_data_instances`.
+    ///
+    /// As readers rely on strict mode for memory safety, it can only be enabled by writers using
+    /// an [`ExclusiveLockStrategy`], which guarantees that no other writer modifies the data
+    /// concurrently.
+    pub fn with_strict_mode(mut self, enabled: bool) -> Self {
+        self.strict = enabled;
+        self
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use crate::instance::InstanceVersion;
-    use crate::locks::SingleWriter;
-    use crate::synchronizer::{Synchronizer, SynchronizerError};
+    use crate::locks::{Failover, LockMode, SingleWriter, StateMmap, WriteLockStrategy};
+    use crate::state::RLOCK_HOOK;
+    #[cfg(feature = "tokio")]
+    use crate::stream::VersionStream;
+    use crate::synchronizer::{ActiveReadersPolicy, Synchronizer, SynchronizerError};
     use bytecheck::CheckBytes;
     use rand::distributions::Uniform;
     use rand::prelude::*;
     use rkyv::{Archive, Deserialize, Serialize};
     use std::collections::HashMap;
-    use std::fs;
+    use std::fs::{self, File};
+    use std::panic::{self, AssertUnwindSafe};
     use std::path::Path;
-    use std::time::Duration;
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::mpsc;
+    use std::thread;
+    use std::time::{Duration, Instant, SystemTime};
     use wyhash::WyHash;
 
     #[derive(Archive, Deserialize, Serialize, Debug, PartialEq)]
@@ -278,6 +1025,12 @@ mod tests {
         map: HashMap<u64, Vec<f32>>,
     }
 
+    #[derive(Archive, Deserialize, Serialize, Debug, PartialEq)]
+    #[archive_attr(derive(CheckBytes))]
+    struct UniformEntity {
+        values: Vec<u64>,
+    }
+
     struct MockEntityGenerator {
         rng: StdRng,
     }
@@ -335,14 +1088,14 @@ mod tests {
 
         // check if can write entity with correct size
         let entity = entity_generator.gen(100);
-        let (size, reset) = writer.write(&entity, Duration::from_secs(1)).unwrap();
-        assert!(size > 0);
-        assert_eq!(reset, false);
+        let res = writer.write(&entity, Duration::from_secs(1)).unwrap();
+        assert!(res.size() > 0);
+        assert!(!res.is_reset());
         assert!(Path::new(&state_path).exists());
         assert!(!Path::new(&data_path_1).exists());
         assert_eq!(
             reader.version().unwrap(),
-            InstanceVersion(8817430144856633152)
+            InstanceVersion(16822976505716980992)
         );
 
         // check that first time scoped `read` works correctly and switches the data
@@ -353,15 +1106,15 @@ mod tests {
 
         // check if can write entity again
         let entity = entity_generator.gen(200);
-        let (size, reset) = writer.write(&entity, Duration::from_secs(1)).unwrap();
-        assert!(size > 0);
-        assert_eq!(reset, false);
+        let res = writer.write(&entity, Duration::from_secs(1)).unwrap();
+        assert!(res.size() > 0);
+        assert!(!res.is_reset());
         assert!(Path::new(&state_path).exists());
         assert!(Path::new(&data_path_0).exists());
         assert!(Path::new(&data_path_1).exists());
         assert_eq!(
             reader.version().unwrap(),
-            InstanceVersion(1441050725688826209)
+            InstanceVersion(5764202902755304833)
         );
 
         // check that another scoped `read` works correctly and switches the data
@@ -369,21 +1122,21 @@ mod tests {
 
         // write entity twice to switch to the same `idx` without any reads in between
         let entity = entity_generator.gen(100);
-        let (size, reset) = writer.write(&entity, Duration::from_secs(1)).unwrap();
-        assert!(size > 0);
-        assert_eq!(reset, false);
+        let res = writer.write(&entity, Duration::from_secs(1)).unwrap();
+        assert!(res.size() > 0);
+        assert!(!res.is_reset());
         assert_eq!(
             reader.version().unwrap(),
-            InstanceVersion(14058099486534675680)
+            InstanceVersion(892165725010047872)
         );
 
         let entity = entity_generator.gen(200);
-        let (size, reset) = writer.write(&entity, Duration::from_secs(1)).unwrap();
-        assert!(size > 0);
-        assert_eq!(reset, false);
+        let res = writer.write(&entity, Duration::from_secs(1)).unwrap();
+        assert!(res.size() > 0);
+        assert!(!res.is_reset());
         assert_eq!(
             reader.version().unwrap(),
-            InstanceVersion(18228729609619266545)
+            InstanceVersion(17574686217348411329)
         );
 
         fetch_and_assert_entity(&mut reader, &entity, true);
@@ -403,6 +1156,7 @@ mod tests {
     #[test]
     fn single_writer_lock_prevents_multiple_writers() {
         static PATH: &str = "/tmp/synchronizer_single_writer";
+        fs::remove_file(PATH.to_owned() + "_state").unwrap_or_default();
         let mut entity_generator = MockEntityGenerator::new(3);
         let entity = entity_generator.gen(100);
 
@@ -415,4 +1169,759 @@ mod tests {
             Err(SynchronizerError::WriteLockConflict)
         ));
     }
+
+    #[test]
+    fn released_write_lock_is_handed_off_to_waiting_writer() {
+        static PATH: &str = "/tmp/synchronizer_lock_handoff";
+        fs::remove_file(PATH.to_owned() + "_state").unwrap_or_default();
+        let mut entity_generator = MockEntityGenerator::new(3);
+        let entity = entity_generator.gen(100);
+
+        let mut writer1 = Synchronizer::<WyHash, SingleWriter>::with_params(PATH.as_ref());
+        writer1.write(&entity, Duration::from_secs(1)).unwrap();
+
+        let mut writer2 = Synchronizer::<WyHash, SingleWriter>::with_params(PATH.as_ref())
+            .with_lock_mode(LockMode::Timeout(Duration::from_millis(20)));
+        assert!(matches!(
+            writer2.write(&entity, Duration::from_secs(1)),
+            Err(SynchronizerError::WriteLockConflict)
+        ));
+
+        let handle = thread::spawn(move || {
+            let mut writer2 = Synchronizer::<WyHash, SingleWriter>::with_params(PATH.as_ref())
+                .with_lock_mode(LockMode::Blocking);
+            writer2.write(&entity, Duration::from_secs(1)).unwrap();
+        });
+        thread::sleep(Duration::from_millis(50));
+        assert!(!handle.is_finished());
+
+        writer1.release_write_lock().unwrap();
+        handle.join().unwrap();
+    }
+
+    #[test]
+    fn fenced_out_writer_leaves_data_of_standby_intact() {
+        static PATH: &str = "/tmp/synchronizer_failover";
+        fs::remove_file(PATH.to_owned() + "_state").unwrap_or_default();
+        let mut entity_generator = MockEntityGenerator::new(3);
+        let primary_entity = entity_generator.gen(100);
+        let standby_entity = entity_generator.gen(200);
+
+        let mut primary = Synchronizer::<WyHash, Failover>::with_params(PATH.as_ref());
+        let mut standby = Synchronizer::<WyHash, Failover>::with_params(PATH.as_ref());
+        let mut reader = Synchronizer::new(PATH.as_ref());
+
+        primary
+            .write(&primary_entity, Duration::from_secs(1))
+            .unwrap();
+        fetch_and_assert_entity(&mut reader, &primary_entity, true);
+
+        // primary releases its lock while it's paused, so that standby takes over and publishes
+        primary.release_write_lock().unwrap();
+        standby
+            .write(&standby_entity, Duration::from_secs(1))
+            .unwrap();
+        fetch_and_assert_entity(&mut reader, &standby_entity, true);
+
+        assert!(matches!(
+            primary.write(&primary_entity, Duration::from_secs(1)),
+            Err(SynchronizerError::FencedOut {
+                epoch: 1,
+                current: 2
+            })
+        ));
+        fetch_and_assert_entity(&mut reader, &standby_entity, false);
+
+        // standby keeps publishing as the only writer
+        let res = standby
+            .write(&primary_entity, Duration::from_secs(1))
+            .unwrap();
+        assert_eq!(reader.generation().unwrap(), res.generation());
+        fetch_and_assert_entity(&mut reader, &primary_entity, true);
+    }
+
+    /// Lock strategy held within an external coordination layer, mocked by a static flag
+    struct ExternalLock {
+        mmap: StateMmap,
+        locked: bool,
+    }
+
+    static EXTERNAL_LOCK_HELD: AtomicBool = AtomicBool::new(false);
+
+    // SAFETY: the lock is held by a single writer at a time, as tracked by the static flag
+    unsafe impl<'a> WriteLockStrategy<'a> for ExternalLock {
+        type Guard = &'a mut StateMmap;
+
+        fn new(mmap: StateMmap, _file: File) -> Self {
+            ExternalLock {
+                mmap,
+                locked: false,
+            }
+        }
+
+        fn read(&'a self) -> &'a [u8] {
+            &self.mmap
+        }
+
+        fn is_locked(&self) -> bool {
+            self.locked
+        }
+
+        fn lock(&'a mut self, _mode: LockMode) -> Result<Self::Guard, SynchronizerError> {
+            if !self.locked {
+                if EXTERNAL_LOCK_HELD.swap(true, Ordering::SeqCst) {
+                    return Err(SynchronizerError::WriteLockConflict);
+                }
+                self.locked = true;
+            }
+            Ok(&mut self.mmap)
+        }
+
+        fn release(&mut self) -> Result<(), SynchronizerError> {
+            if self.locked {
+                EXTERNAL_LOCK_HELD.store(false, Ordering::SeqCst);
+                self.locked = false;
+            }
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn custom_lock_strategy_prevents_multiple_writers() {
+        static PATH: &str = "/tmp/synchronizer_custom_lock";
+        fs::remove_file(PATH.to_owned() + "_state").unwrap_or_default();
+        let mut entity_generator = MockEntityGenerator::new(3);
+        let entity = entity_generator.gen(100);
+
+        let mut writer1 = Synchronizer::<WyHash, ExternalLock>::with_params(PATH.as_ref());
+        let mut writer2 = Synchronizer::<WyHash, ExternalLock>::with_params(PATH.as_ref());
+
+        writer1.write(&entity, Duration::from_secs(1)).unwrap();
+        assert!(matches!(
+            writer2.write(&entity, Duration::from_secs(1)),
+            Err(SynchronizerError::WriteLockConflict)
+        ));
+
+        writer1.release_write_lock().unwrap();
+        writer2.write(&entity, Duration::from_secs(1)).unwrap();
+        writer2.release_write_lock().unwrap();
+    }
+
+    #[test]
+    fn generation_distinguishes_identical_writes() {
+        static PATH: &str = "/tmp/synchronizer_generation";
+        fs::remove_file(PATH.to_owned() + "_state").unwrap_or_default();
+        let mut entity_generator = MockEntityGenerator::new(3);
+        let entity = entity_generator.gen(100);
+
+        let mut writer = Synchronizer::new(PATH.as_ref());
+        let mut reader = Synchronizer::new(PATH.as_ref());
+
+        let first = writer.write(&entity, Duration::from_secs(1)).unwrap();
+        let version = reader.version().unwrap();
+        let res = unsafe { reader.read::<MockEntity>(false).unwrap() };
+        assert!(res.is_switched());
+        assert_eq!(res.generation(), first.generation());
+        drop(res);
+
+        // writing the same entity twice ends up with exactly the same version
+        writer.write(&entity, Duration::from_secs(1)).unwrap();
+        let third = writer.write(&entity, Duration::from_secs(1)).unwrap();
+        assert_eq!(reader.version().unwrap(), version);
+        assert_eq!(third.generation(), first.generation() + 2);
+        assert_eq!(reader.generation().unwrap(), third.generation());
+
+        let res = unsafe { reader.read::<MockEntity>(false).unwrap() };
+        assert!(res.is_switched());
+        assert_eq!(res.generation(), third.generation());
+    }
+
+    #[test]
+    fn checksum_verification_detects_corrupted_data() {
+        static PATH: &str = "/tmp/synchronizer_checksum";
+        fs::remove_file(PATH.to_owned() + "_state").unwrap_or_default();
+        let mut entity_generator = MockEntityGenerator::new(3);
+        let mut writer = Synchronizer::new(PATH.as_ref());
+        let mut reader = Synchronizer::new(PATH.as_ref()).with_checksum_verification(true);
+
+        writer
+            .write(&entity_generator.gen(100), Duration::from_secs(1))
+            .unwrap();
+        let idx = reader.version().unwrap().idx();
+        assert!(unsafe { reader.read::<MockEntity>(true) }.is_ok());
+
+        // flip a bit within the data file behind the writer's back
+        let data_path = format!("{PATH}_data_{idx}");
+        let mut data = fs::read(&data_path).unwrap();
+        data[0] ^= 1;
+        fs::write(&data_path, data).unwrap();
+
+        assert!(matches!(
+            unsafe { reader.read::<MockEntity>(true) },
+            Err(SynchronizerError::ChecksumMismatch)
+        ));
+    }
+
+    #[test]
+    fn owned_read_result_outlives_borrow_of_synchronizer() {
+        static PATH: &str = "/tmp/synchronizer_owned_read";
+        fs::remove_file(PATH.to_owned() + "_state").unwrap_or_default();
+        let mut writer = Synchronizer::new(PATH.as_ref());
+        let mut reader = Synchronizer::new(PATH.as_ref());
+
+        let entity = UniformEntity {
+            values: vec![1, 2, 3],
+        };
+        writer.write(&entity, Duration::from_secs(1)).unwrap();
+        let owned = unsafe { reader.read_owned::<UniformEntity>(true).unwrap() };
+        assert!(owned.is_switched());
+        let leased = unsafe {
+            reader
+                .read_owned_with_lease::<UniformEntity>(true, Duration::from_secs(10))
+                .unwrap()
+        };
+        assert!(!leased.is_switched());
+
+        // reader keeps reading newer data while owned results hold the older one
+        let newer = UniformEntity {
+            values: (0..1000).collect(),
+        };
+        writer.write(&newer, Duration::from_secs(1)).unwrap();
+        let res = unsafe { reader.read::<UniformEntity>(true).unwrap() };
+        assert_eq!(res.values.as_slice(), newer.values);
+        drop(res);
+
+        let values = thread::spawn(move || owned.values.to_vec()).join().unwrap();
+        assert_eq!(values, entity.values);
+        assert!(leased.is_still_valid());
+        assert_eq!(leased.values.as_slice(), entity.values);
+    }
+
+    #[test]
+    fn read_of_mismatched_type_is_rejected() {
+        static PATH: &str = "/tmp/synchronizer_type_mismatch";
+        fs::remove_file(PATH.to_owned() + "_state").unwrap_or_default();
+        let mut entity_generator = MockEntityGenerator::new(3);
+        let mut writer = Synchronizer::new(PATH.as_ref());
+        let mut reader = Synchronizer::new(PATH.as_ref());
+
+        writer
+            .write(&entity_generator.gen(100), Duration::from_secs(1))
+            .unwrap();
+        assert!(unsafe { reader.read::<MockEntity>(false) }.is_ok());
+        assert!(matches!(
+            unsafe { reader.read::<UniformEntity>(false) },
+            Err(SynchronizerError::TypeMismatch)
+        ));
+    }
+
+    #[test]
+    fn read_of_same_named_type_with_other_layout_is_rejected() {
+        static PATH: &str = "/tmp/synchronizer_type_layout_mismatch";
+        fs::remove_file(PATH.to_owned() + "_state").unwrap_or_default();
+
+        /// Versions of the same struct, the later one with a field added
+        mod v1 {
+            use super::*;
+
+            #[derive(Archive, Deserialize, Serialize, Debug, PartialEq)]
+            #[archive_attr(derive(CheckBytes))]
+            pub(super) struct Entity {
+                pub(super) values: Vec<u64>,
+            }
+        }
+        mod v2 {
+            use super::*;
+
+            #[derive(Archive, Deserialize, Serialize, Debug, PartialEq)]
+            #[archive_attr(derive(CheckBytes))]
+            pub(super) struct Entity {
+                pub(super) values: Vec<u64>,
+                pub(super) total: u64,
+            }
+        }
+
+        let mut writer = Synchronizer::new(PATH.as_ref());
+        let mut reader = Synchronizer::new(PATH.as_ref());
+        let entity = v1::Entity {
+            values: vec![1, 2, 3],
+        };
+        writer.write(&entity, Duration::from_secs(1)).unwrap();
+
+        assert!(unsafe { reader.read::<v1::Entity>(false) }.is_ok());
+        assert!(matches!(
+            unsafe { reader.read::<v2::Entity>(false) },
+            Err(SynchronizerError::TypeMismatch)
+        ));
+    }
+
+    #[test]
+    fn type_check_can_be_disabled_for_mirrored_types() {
+        static PATH: &str = "/tmp/synchronizer_type_check";
+        fs::remove_file(PATH.to_owned() + "_state").unwrap_or_default();
+
+        /// Type mirroring the layout of `UniformEntity` under another name
+        #[derive(Archive, Deserialize, Serialize, Debug, PartialEq)]
+        #[archive_attr(derive(CheckBytes))]
+        struct MirroredEntity {
+            values: Vec<u64>,
+        }
+
+        let mut writer = Synchronizer::new(PATH.as_ref());
+        let mut reader = Synchronizer::new(PATH.as_ref());
+        let mut unchecked_reader = Synchronizer::new(PATH.as_ref()).with_type_check(false);
+        let entity = UniformEntity {
+            values: vec![1, 2, 3],
+        };
+        writer.write(&entity, Duration::from_secs(1)).unwrap();
+
+        assert!(matches!(
+            unsafe { reader.read::<MirroredEntity>(true) },
+            Err(SynchronizerError::TypeMismatch)
+        ));
+        let res = unsafe { unchecked_reader.read::<MirroredEntity>(true).unwrap() };
+        assert_eq!(res.values.as_slice(), entity.values);
+    }
+
+    #[test]
+    fn metadata_describes_publication() {
+        static PATH: &str = "/tmp/synchronizer_metadata";
+        fs::remove_file(PATH.to_owned() + "_state").unwrap_or_default();
+        let mut entity_generator = MockEntityGenerator::new(3);
+        let mut writer = Synchronizer::new(PATH.as_ref());
+        let mut reader = Synchronizer::new(PATH.as_ref());
+        let mut fresh_reader =
+            Synchronizer::new(PATH.as_ref()).with_max_age(Duration::from_secs(60));
+        let mut strict_reader =
+            Synchronizer::new(PATH.as_ref()).with_max_age(Duration::from_millis(10));
+
+        let written_at = SystemTime::now();
+        writer
+            .write(&entity_generator.gen(100), Duration::from_secs(1))
+            .unwrap();
+        let metadata = reader.metadata().unwrap();
+        assert_eq!(metadata.writer_pid(), std::process::id());
+        assert!(metadata.published_at() >= written_at);
+        assert!(unsafe { fresh_reader.read::<MockEntity>(false) }.is_ok());
+
+        let res = unsafe { reader.read::<MockEntity>(false).unwrap() };
+        assert_eq!(res.metadata(), metadata);
+        drop(res);
+
+        thread::sleep(Duration::from_millis(20));
+        assert!(matches!(
+            unsafe { strict_reader.read::<MockEntity>(false) },
+            Err(SynchronizerError::StaleData { age }) if age >= Duration::from_millis(20)
+        ));
+    }
+
+    #[test]
+    fn heartbeat_tells_whether_writer_is_alive() {
+        static PATH: &str = "/tmp/synchronizer_heartbeat";
+        fs::remove_file(PATH.to_owned() + "_state").unwrap_or_default();
+        let mut writer = Synchronizer::new(PATH.as_ref());
+        let mut reader = Synchronizer::new(PATH.as_ref());
+
+        writer.heartbeat().unwrap();
+        assert!(reader.last_heartbeat().unwrap().is_some());
+        assert!(reader.is_writer_alive(Duration::from_secs(60)).unwrap());
+
+        // background heartbeat keeps writer alive without any writes
+        let heartbeat = writer.spawn_heartbeat(Duration::from_millis(1)).unwrap();
+        wait_for_heartbeat_after(&mut reader, SystemTime::now());
+
+        // no heartbeats are recorded once the thread is stopped
+        drop(heartbeat);
+        let last_heartbeat = reader.last_heartbeat().unwrap();
+        thread::sleep(Duration::from_millis(20));
+        assert_eq!(reader.last_heartbeat().unwrap(), last_heartbeat);
+        assert!(!reader.is_writer_alive(Duration::from_millis(10)).unwrap());
+    }
+
+    #[test]
+    fn heartbeat_stops_once_write_lock_is_released() {
+        static PATH: &str = "/tmp/synchronizer_heartbeat_release";
+        fs::remove_file(PATH.to_owned() + "_state").unwrap_or_default();
+        let mut writer = Synchronizer::<WyHash, SingleWriter>::with_params(PATH.as_ref());
+        let mut reader = Synchronizer::new(PATH.as_ref());
+
+        let _heartbeat = writer.spawn_heartbeat(Duration::from_millis(1)).unwrap();
+        wait_for_heartbeat_after(&mut reader, SystemTime::now());
+
+        // heartbeat recorded right before the lock got released might still land
+        writer.release_write_lock().unwrap();
+        thread::sleep(Duration::from_millis(20));
+        let last_heartbeat = reader.last_heartbeat().unwrap();
+        thread::sleep(Duration::from_millis(20));
+        assert_eq!(reader.last_heartbeat().unwrap(), last_heartbeat);
+    }
+
+    #[test]
+    fn heartbeat_stops_once_writer_is_dropped() {
+        static PATH: &str = "/tmp/synchronizer_heartbeat_drop";
+        fs::remove_file(PATH.to_owned() + "_state").unwrap_or_default();
+        let mut writer = Synchronizer::<WyHash, SingleWriter>::with_params(PATH.as_ref());
+        let mut reader = Synchronizer::new(PATH.as_ref());
+
+        let _heartbeat = writer.spawn_heartbeat(Duration::from_millis(1)).unwrap();
+        wait_for_heartbeat_after(&mut reader, SystemTime::now());
+
+        // heartbeat handle outliving the writer stops recording heartbeats
+        drop(writer);
+        thread::sleep(Duration::from_millis(20));
+        let last_heartbeat = reader.last_heartbeat().unwrap();
+        thread::sleep(Duration::from_millis(20));
+        assert_eq!(reader.last_heartbeat().unwrap(), last_heartbeat);
+        assert!(!reader.is_writer_alive(Duration::from_millis(10)).unwrap());
+    }
+
+    /// Waits for `reader` to observe writer heartbeat recorded after `since`
+    fn wait_for_heartbeat_after(reader: &mut Synchronizer, since: SystemTime) {
+        let deadline = Instant::now() + Duration::from_secs(10);
+        while reader.last_heartbeat().unwrap() <= Some(since) {
+            assert!(Instant::now() < deadline, "no heartbeat recorded");
+            thread::sleep(Duration::from_millis(1));
+        }
+    }
+
+    #[test]
+    fn writer_skips_data_instances_held_by_readers() {
+        static PATH: &str = "/tmp/synchronizer_data_instances";
+        fs::remove_file(PATH.to_owned() + "_state").unwrap_or_default();
+        let mut entity_generator = MockEntityGenerator::new(3);
+        let mut writer = Synchronizer::new(PATH.as_ref()).with_data_instances(3);
+        let mut reader = Synchronizer::new(PATH.as_ref());
+
+        writer
+            .write(&entity_generator.gen(10), Duration::from_secs(1))
+            .unwrap();
+        assert_eq!(writer.version().unwrap().idx(), 0);
+        let res = unsafe { reader.read::<MockEntity>(false).unwrap() };
+
+        // writer keeps cycling through data instances not held by the reader without waiting
+        let mut indices = vec![];
+        for n in 0..4 {
+            let entity = entity_generator.gen(10 + n);
+            let res = writer.write(&entity, Duration::from_secs(60)).unwrap();
+            assert!(!res.is_reset());
+            indices.push(writer.version().unwrap().idx());
+        }
+        assert_eq!(indices, [1, 2, 1, 2]);
+        drop(res);
+
+        writer
+            .write(&entity_generator.gen(20), Duration::from_secs(1))
+            .unwrap();
+        assert_eq!(writer.version().unwrap().idx(), 0);
+    }
+
+    #[test]
+    fn fail_policy_never_evicts_active_readers() {
+        static PATH: &str = "/tmp/synchronizer_fail_policy";
+        fs::remove_file(PATH.to_owned() + "_state").unwrap_or_default();
+        let mut entity_generator = MockEntityGenerator::new(3);
+        let mut writer =
+            Synchronizer::new(PATH.as_ref()).with_active_readers_policy(ActiveReadersPolicy::Fail);
+        let mut reader = Synchronizer::new(PATH.as_ref());
+
+        let entity = entity_generator.gen(10);
+        writer.write(&entity, Duration::from_secs(1)).unwrap();
+        let res = unsafe { reader.read::<MockEntity>(false).unwrap() };
+        let written = writer.try_write(&entity).unwrap();
+        assert_eq!(written.evicted_readers(), 0);
+
+        // both data files are held now: the reader holds the older one
+        assert!(matches!(
+            writer.write(&entity, Duration::from_millis(10)),
+            Err(SynchronizerError::ReadersStillActive { readers: 1 })
+        ));
+        assert!(matches!(
+            writer.try_write(&entity),
+            Err(SynchronizerError::ReadersStillActive { readers: 1 })
+        ));
+
+        drop(res);
+        assert!(writer.try_write(&entity).is_ok());
+    }
+
+    #[test]
+    fn safe_read_requires_strict_mode() {
+        static PATH: &str = "/tmp/synchronizer_strict_mode";
+        fs::remove_file(PATH.to_owned() + "_state").unwrap_or_default();
+        let mut entity_generator = MockEntityGenerator::new(3);
+        let entity = entity_generator.gen(10);
+        let mut reader = Synchronizer::new(PATH.as_ref());
+
+        let mut writer = Synchronizer::new(PATH.as_ref());
+        writer.write(&entity, Duration::from_secs(1)).unwrap();
+        assert!(matches!(
+            reader.safe_read::<MockEntity>(),
+            Err(SynchronizerError::StrictModeDisabled)
+        ));
+
+        let mut writer =
+            Synchronizer::<WyHash, SingleWriter>::with_params(PATH.as_ref()).with_strict_mode(true);
+        writer.write(&entity, Duration::from_secs(1)).unwrap();
+        let res = reader.safe_read::<MockEntity>().unwrap();
+        assert_eq!(res.version, entity.version);
+    }
+
+    #[test]
+    fn read_result_stays_valid_until_reset() {
+        static PATH: &str = "/tmp/synchronizer_validity";
+        fs::remove_file(PATH.to_owned() + "_state").unwrap_or_default();
+        let mut entity_generator = MockEntityGenerator::new(3);
+        let mut writer = Synchronizer::new(PATH.as_ref());
+        let mut reader = Synchronizer::new(PATH.as_ref()).with_validity_assertion(true);
+
+        writer
+            .write(&entity_generator.gen(10), Duration::from_secs(1))
+            .unwrap();
+        let res = unsafe { reader.read::<MockEntity>(false).unwrap() };
+        writer
+            .write(&entity_generator.gen(10), Duration::from_secs(1))
+            .unwrap();
+        assert!(res.is_still_valid());
+
+        // writer resets the reader once grace period of the next write into its data file expires
+        let write_result = writer
+            .write(&entity_generator.gen(10), Duration::ZERO)
+            .unwrap();
+        assert!(write_result.is_reset());
+        assert!(!res.is_still_valid());
+
+        // invalid result is dropped quietly while unwinding from another panic
+        let panic = panic::catch_unwind(AssertUnwindSafe(move || {
+            let _res = res;
+            panic!("reader failed");
+        }));
+        assert_eq!(
+            *panic.unwrap_err().downcast::<&str>().unwrap(),
+            "reader failed"
+        );
+    }
+
+    #[cfg(debug_assertions)]
+    #[test]
+    #[should_panic(expected = "outlived grace period")]
+    fn dropping_reset_read_result_panics_with_validity_assertion() {
+        static PATH: &str = "/tmp/synchronizer_validity_assertion";
+        fs::remove_file(PATH.to_owned() + "_state").unwrap_or_default();
+        let mut entity_generator = MockEntityGenerator::new(3);
+        let mut writer = Synchronizer::new(PATH.as_ref());
+        let mut reader = Synchronizer::new(PATH.as_ref()).with_validity_assertion(true);
+
+        writer
+            .write(&entity_generator.gen(10), Duration::from_secs(1))
+            .unwrap();
+        let res = unsafe { reader.read::<MockEntity>(false).unwrap() };
+        for _ in 0..2 {
+            writer
+                .write(&entity_generator.gen(10), Duration::ZERO)
+                .unwrap();
+        }
+        assert!(!res.is_still_valid());
+        drop(res);
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn try_write_never_waits_for_leases() {
+        static PATH: &str = "/tmp/synchronizer_try_write_lease";
+        fs::remove_file(PATH.to_owned() + "_state").unwrap_or_default();
+        let mut entity_generator = MockEntityGenerator::new(3);
+        let mut writer = Synchronizer::new(PATH.as_ref());
+        let mut reader = Synchronizer::new(PATH.as_ref());
+
+        writer
+            .write(&entity_generator.gen(10), Duration::from_secs(1))
+            .unwrap();
+        let res = unsafe {
+            reader
+                .read_with_lease::<MockEntity>(false, Duration::from_secs(60))
+                .unwrap()
+        };
+        assert!(res.extend_lease(Duration::from_secs(60)));
+        writer.try_write(&entity_generator.gen(10)).unwrap();
+
+        let started_at = Instant::now();
+        assert!(matches!(
+            writer.try_write(&entity_generator.gen(10)),
+            Err(SynchronizerError::ReadersStillActive { readers: 1 })
+        ));
+        assert!(started_at.elapsed() < Duration::from_secs(10));
+        assert!(res.is_still_valid());
+    }
+
+    #[test]
+    fn mapped_read_result_holds_reader_lock() {
+        static PATH: &str = "/tmp/synchronizer_mapped_read";
+        fs::remove_file(PATH.to_owned() + "_state").unwrap_or_default();
+        let mut writer = Synchronizer::new(PATH.as_ref());
+        let mut reader = Synchronizer::new(PATH.as_ref());
+        let entity = UniformEntity {
+            values: vec![1, 2, 3],
+        };
+        writer.write(&entity, Duration::from_secs(1)).unwrap();
+
+        let res = unsafe { reader.read::<UniformEntity>(true).unwrap() };
+        let generation = res.generation();
+        let values = res.map(|entity| &entity.values).map(|values| &values[1..]);
+        assert_eq!(*values, [2, 3]);
+        assert_eq!(values.generation(), generation);
+
+        // mapped result keeps the data file locked until dropped
+        assert!(writer.try_write(&entity).is_ok());
+        assert!(matches!(
+            writer.try_write(&entity),
+            Err(SynchronizerError::ReadersStillActive { readers: 1 })
+        ));
+        drop(values);
+        assert!(writer.try_write(&entity).is_ok());
+    }
+
+    #[test]
+    fn concurrent_reads_never_observe_torn_data() {
+        static PATH: &str = "/tmp/synchronizer_torn_read";
+        const NUM_VALUES: usize = 4 * 1024;
+
+        fs::remove_file(PATH.to_owned() + "_state").unwrap_or_default();
+
+        // every written entity consists of equal values, with the number of values depending on
+        // them, so that data of any other version than the locked one never passes the checks
+        let entity = |i: u64| UniformEntity {
+            values: vec![i; NUM_VALUES + i as usize],
+        };
+        let mut writer = Synchronizer::new(PATH.as_ref());
+        writer.write(&entity(0), Duration::from_secs(1)).unwrap();
+
+        let (switch_tx, switch_rx) = mpsc::channel();
+        let (switched_tx, switched_rx) = mpsc::channel();
+        let reader = thread::spawn(move || {
+            // let writer switch versions twice between loading the version and registering as
+            // a reader, so that it overwrites data file of the loaded version in the meantime
+            let mut channels = Some((switch_tx, switched_rx));
+            RLOCK_HOOK.with(|hook| {
+                *hook.borrow_mut() = Some(Box::new(move || {
+                    if let Some((switch_tx, switched_rx)) = channels.take() {
+                        switch_tx.send(()).unwrap();
+                        switched_rx.recv().unwrap();
+                    }
+                }))
+            });
+
+            let mut reader = Synchronizer::new(PATH.as_ref());
+            match unsafe { reader.read::<UniformEntity>(true) } {
+                Ok(res) => {
+                    assert_eq!(res.values.len(), NUM_VALUES + 2);
+                    assert!(res.values.iter().all(|v| *v == 2));
+                }
+                Err(SynchronizerError::VersionChangedDuringRead) => {}
+                Err(err) => panic!("unexpected read error: {err}"),
+            };
+        });
+
+        switch_rx.recv().unwrap();
+        for i in 1..=2 {
+            let res = writer.write(&entity(i), Duration::from_secs(1)).unwrap();
+            assert!(!res.is_reset());
+        }
+        assert_eq!(writer.version().unwrap().idx(), 0);
+        switched_tx.send(()).unwrap();
+        reader.join().unwrap();
+    }
+
+    #[cfg(feature = "tokio")]
+    #[tokio::test]
+    async fn changed_resolves_on_new_version() {
+        static PATH: &str = "/tmp/synchronizer_changed";
+        fs::remove_file(PATH.to_owned() + "_state").unwrap_or_default();
+        let mut entity_generator = MockEntityGenerator::new(3);
+        let mut writer = Synchronizer::new(PATH.as_ref());
+        writer
+            .write(&entity_generator.gen(10), Duration::from_secs(1))
+            .unwrap();
+
+        let mut reader = Synchronizer::new(PATH.as_ref());
+        let last = reader.version().unwrap();
+
+        let entity = entity_generator.gen(20);
+        let handle = thread::spawn(move || {
+            thread::sleep(Duration::from_millis(50));
+            writer.write(&entity, Duration::from_secs(1)).unwrap();
+        });
+
+        let version = reader.changed(last).await.unwrap();
+        assert_ne!(version, last);
+        handle.join().unwrap();
+    }
+
+    #[cfg(feature = "tokio")]
+    #[tokio::test]
+    async fn changes_stream_yields_published_versions() {
+        use futures_core::Stream;
+        use std::future::poll_fn;
+        use std::pin::Pin;
+
+        static PATH: &str = "/tmp/synchronizer_changes";
+        fs::remove_file(PATH.to_owned() + "_state").unwrap_or_default();
+        let mut entity_generator = MockEntityGenerator::new(3);
+        let mut writer = Synchronizer::new(PATH.as_ref());
+        writer
+            .write(&entity_generator.gen(10), Duration::from_secs(1))
+            .unwrap();
+
+        let mut reader = Synchronizer::new(PATH.as_ref());
+        let current = reader.version().unwrap();
+        let mut changes = reader.changes().unwrap();
+        let next = poll_fn(|cx| Pin::new(&mut changes).poll_next(cx)).await;
+        assert_eq!(next.unwrap().unwrap(), current);
+
+        writer
+            .write(&entity_generator.gen(20), Duration::from_secs(1))
+            .unwrap();
+        let next = poll_fn(|cx| Pin::new(&mut changes).poll_next(cx)).await;
+        assert_ne!(next.unwrap().unwrap(), current);
+
+        // stream keeps waiting until the next version is published
+        let next = poll_fn(|cx| Pin::new(&mut changes).poll_next(cx));
+        assert!(tokio::time::timeout(Duration::from_millis(50), next)
+            .await
+            .is_err());
+        drop(changes);
+    }
+
+    #[cfg(feature = "tokio")]
+    #[tokio::test]
+    async fn change_notifications_share_state_mapping() {
+        use futures_core::Stream;
+        use std::future::poll_fn;
+        use std::pin::Pin;
+
+        static PATH: &str = "/tmp/synchronizer_changes_shared";
+        fs::remove_file(PATH.to_owned() + "_state").unwrap_or_default();
+        let mut entity_generator = MockEntityGenerator::new(3);
+        let mut writer = Synchronizer::new(PATH.as_ref());
+        writer
+            .write(&entity_generator.gen(10), Duration::from_secs(1))
+            .unwrap();
+
+        // all the notifications of a synchronizer use the same mapping and watcher
+        let mut reader = Synchronizer::new(PATH.as_ref());
+        let first = reader.state_container.shared_state().unwrap();
+        let second = reader.state_container.shared_state().unwrap();
+        assert!(std::ptr::eq(&*first, &*second));
+
+        let last = reader.version().unwrap();
+        let mut streams: Vec<_> = (0..16)
+            .map(|_| VersionStream::new(first.clone(), Some(last)))
+            .collect();
+        writer
+            .write(&entity_generator.gen(20), Duration::from_secs(1))
+            .unwrap();
+        for changes in &mut streams {
+            let next = poll_fn(|cx| Pin::new(&mut *changes).poll_next(cx)).await;
+            assert_ne!(next.unwrap().unwrap(), last);
+        }
+    }
 }
@@ -1,7 +1,16 @@
+use bytemuck::Pod;
+use rkyv::{Archive, Archived};
 use std::fs::File;
+use std::hash::Hasher;
 use std::io;
+use std::mem;
 #[cfg(target_os = "linux")]
 use std::os::fd::AsRawFd;
+#[cfg(target_os = "linux")]
+use std::sync::atomic::{AtomicU32, AtomicU64, Ordering};
+use std::sync::OnceLock;
+use std::time::{SystemTime, UNIX_EPOCH};
+use wyhash::WyHash;
 
 /// Set the length of the file to the specified length.
 pub(crate) fn set_len(file: &File, len: i64) -> Result<(), io::Error> {
@@ -16,3 +25,241 @@ pub(crate) fn set_len(file: &File, len: i64) -> Result<(), io::Error> {
     #[cfg(not(target_os = "linux"))]
     file.set_len(len as u64)
 }
+
+/// Identity of a reader process recorded within the registry of readers
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) struct Process {
+    /// Id of the process within its pid namespace
+    pub(crate) pid: u32,
+    /// Start time of the process, guarding against process id reuse
+    pub(crate) start_time: u64,
+    /// Inode of the pid namespace of the process
+    pub(crate) pid_ns: u64,
+}
+
+/// Return identity of the current process.
+///
+/// Process identity is only available on Linux platforms with `/proc` mounted for the pid
+/// namespace of the current process, so `None` is returned elsewhere.
+#[cfg(target_os = "linux")]
+pub(crate) fn current_process() -> Option<Process> {
+    use std::os::unix::fs::MetadataExt;
+
+    static PID: AtomicU32 = AtomicU32::new(0);
+    static START_TIME: AtomicU64 = AtomicU64::new(0);
+    static PID_NS: AtomicU64 = AtomicU64::new(0);
+
+    // cached value is keyed by process id, so that it gets refreshed in forked processes
+    let pid = std::process::id();
+    if PID.load(Ordering::SeqCst) != pid {
+        // process ids found in `/proc` mounted for another pid namespace refer to other processes
+        let proc_pid = std::fs::read_link("/proc/self").ok()?;
+        if proc_pid.to_str()?.parse::<u32>().ok()? != pid {
+            return None;
+        }
+        let pid_ns = std::fs::metadata("/proc/self/ns/pid").ok()?.ino();
+
+        START_TIME.store(process_start_time(pid)?, Ordering::SeqCst);
+        PID_NS.store(pid_ns, Ordering::SeqCst);
+        PID.store(pid, Ordering::SeqCst);
+    }
+
+    Some(Process {
+        pid,
+        start_time: START_TIME.load(Ordering::SeqCst),
+        pid_ns: PID_NS.load(Ordering::SeqCst),
+    })
+}
+
+#[cfg(not(target_os = "linux"))]
+pub(crate) fn current_process() -> Option<Process> {
+    None
+}
+
+/// Check whether process with given `pid` within `pid_ns` namespace and optional `start_time`
+/// is still running.
+///
+/// The start time guards against process id reuse. In case of any doubt, e.g. when process
+/// belongs to another pid namespace, or process information is not accessible to the current
+/// user, the process is considered alive.
+#[cfg(target_os = "linux")]
+pub(crate) fn is_process_alive(pid: u32, pid_ns: u64, start_time: Option<u64>) -> bool {
+    // process ids are meaningless outside of their pid namespace
+    if current_process().is_none_or(|current| current.pid_ns != pid_ns) {
+        return true;
+    }
+
+    if unsafe { libc::kill(pid as libc::pid_t, 0) } != 0
+        && io::Error::last_os_error().raw_os_error() == Some(libc::ESRCH)
+    {
+        return false;
+    }
+
+    match (start_time, process_start_time(pid)) {
+        (Some(start_time), Some(actual_start_time)) => actual_start_time == start_time,
+        _ => true,
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+pub(crate) fn is_process_alive(_pid: u32, _pid_ns: u64, _start_time: Option<u64>) -> bool {
+    true
+}
+
+/// Read start time of the process with given `pid` in clock ticks since boot from `/proc`.
+#[cfg(target_os = "linux")]
+fn process_start_time(pid: u32) -> Option<u64> {
+    let stat = std::fs::read_to_string(format!("/proc/{pid}/stat")).ok()?;
+    // process name may contain spaces and parentheses, so skip everything up to its end
+    let (_, fields) = stat.rsplit_once(')')?;
+    // start time is the 22nd field, while remaining fields start from the 3rd one
+    fields.split_whitespace().nth(19)?.parse().ok()
+}
+
+/// Return fingerprint of archived type `T`, used to detect readers and writers disagreeing on the
+/// type of synchronized entity.
+///
+/// Fingerprint is a best-effort check combining size and alignment of `Archived<T>` with the name
+/// of `T` stripped of module paths, so that the same type compiled into different binaries or
+/// modules matches. Layout of fields is not covered, hence types of the same name and size, e.g.
+/// different versions of the same struct with reordered fields, are not told apart, while types
+/// of the same layout but different names are. Type names are not guaranteed to be stable across
+/// compiler versions either, hence readers and writers are expected to be built with the same
+/// compiler.
+pub(crate) fn type_fingerprint<T: Archive>() -> u64 {
+    layout_fingerprint::<Archived<T>>(std::any::type_name::<T>())
+}
+
+/// Compute fingerprint of plain-old-data type `T` stored as is, the same way as for archived types
+pub(crate) fn pod_type_fingerprint<T: Pod>() -> u64 {
+    layout_fingerprint::<T>(std::any::type_name::<T>())
+}
+
+/// Fingerprint of the archived type read last, cached by readers so that it's computed only once
+/// as long as they keep reading the same type
+#[derive(Default)]
+pub(crate) struct FingerprintCache(OnceLock<(FingerprintKey, u64)>);
+
+/// Address and length of the type name along with size and alignment of the archived type,
+/// identifying the fingerprint without comparing type names
+type FingerprintKey = (usize, usize, usize, usize);
+
+impl FingerprintCache {
+    /// Return fingerprint of archived type `T`, computing it unless it's cached already
+    #[inline]
+    pub(crate) fn type_fingerprint<T: Archive>(&self) -> u64 {
+        // type names are static strings, so their addresses identify them along with the layout
+        let type_name = std::any::type_name::<T>();
+        let key = (
+            type_name.as_ptr() as usize,
+            type_name.len(),
+            mem::size_of::<Archived<T>>(),
+            mem::align_of::<Archived<T>>(),
+        );
+        match self.0.get() {
+            Some((cached_key, fingerprint)) if *cached_key == key => *fingerprint,
+            _ => {
+                let fingerprint = type_fingerprint::<T>();
+                // only the type read first is cached, which is the only one read in most cases
+                let _ = self.0.set((key, fingerprint));
+                fingerprint
+            }
+        }
+    }
+}
+
+/// Compute fingerprint of type named `type_name` stored with the layout of type `L`
+fn layout_fingerprint<L>(type_name: &str) -> u64 {
+    let mut hasher = WyHash::default();
+    hasher.write_usize(mem::size_of::<L>());
+    hasher.write_usize(mem::align_of::<L>());
+    hasher.write(strip_module_paths(type_name).as_bytes());
+    hasher.finish()
+}
+
+/// Strip module paths from all the types mentioned within given `type_name`,
+/// e.g. `alloc::vec::Vec<my::Entity>` becomes `Vec<Entity>`.
+fn strip_module_paths(type_name: &str) -> String {
+    let mut stripped = String::with_capacity(type_name.len());
+    let mut segment_start = 0;
+    let mut chars = type_name.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            ':' if chars.peek() == Some(&':') => {
+                chars.next();
+                stripped.truncate(segment_start);
+            }
+            c if c.is_alphanumeric() || c == '_' => stripped.push(c),
+            c => {
+                stripped.push(c);
+                segment_start = stripped.len();
+            }
+        }
+    }
+    stripped
+}
+
+/// Return given wall-clock `time` in nanoseconds since Unix epoch, or zero for earlier times
+pub(crate) fn unix_nanos(time: SystemTime) -> u64 {
+    time.duration_since(UNIX_EPOCH)
+        .map_or(0, |since_epoch| since_epoch.as_nanos() as u64)
+}
+
+/// Return hash of the current host name, or zero if it is not available
+#[cfg(unix)]
+pub(crate) fn hostname_hash() -> u64 {
+    let mut buf = [0u8; 256];
+    if unsafe { libc::gethostname(buf.as_mut_ptr() as *mut libc::c_char, buf.len()) } != 0 {
+        return 0;
+    }
+    let len = buf.iter().position(|b| *b == 0).unwrap_or(buf.len());
+
+    let mut hasher = WyHash::default();
+    hasher.write(&buf[..len]);
+    hasher.finish()
+}
+
+#[cfg(not(unix))]
+pub(crate) fn hostname_hash() -> u64 {
+    0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn strips_module_paths() {
+        assert_eq!(
+            strip_module_paths("alloc::vec::Vec<writer::common::HelloWorld>"),
+            "Vec<HelloWorld>"
+        );
+        assert_eq!(
+            strip_module_paths("(u32, &[core::option::Option<a::B>; 2])"),
+            "(u32, &[Option<B>; 2])"
+        );
+        assert_ne!(
+            type_fingerprint::<Vec<u32>>(),
+            type_fingerprint::<Vec<u64>>()
+        );
+        assert_eq!(
+            type_fingerprint::<Vec<u32>>(),
+            layout_fingerprint::<Archived<Vec<u32>>>("Vec<u32>")
+        );
+    }
+
+    #[test]
+    fn caches_fingerprint_of_first_type() {
+        let cache = FingerprintCache::default();
+        for _ in 0..2 {
+            assert_eq!(
+                cache.type_fingerprint::<Vec<u32>>(),
+                type_fingerprint::<Vec<u32>>()
+            );
+            assert_eq!(
+                cache.type_fingerprint::<Vec<u64>>(),
+                type_fingerprint::<Vec<u64>>()
+            );
+        }
+    }
+}